@@ -2,8 +2,9 @@ use core::fmt::Debug;
 use std::ops::Deref;
 use std::fmt;
 use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Var(pub String);
 
 impl Var {
@@ -0,0 +1,147 @@
+// A DataFrame of three columns (subject, link, goal) drawn as a directed graph, the links
+// being the labels of the edges, for Graphviz (`dot -Tsvg`) or Mermaid.
+
+use std::collections::HashMap;
+use crate::simple_context::DataFrame;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GraphFormat {
+    #[default]
+    Dot,
+    Mermaid
+}
+
+static GRAPH_FORMATS: [&str; 2] = ["dot", "mermaid"];
+
+pub fn graph_format_names() -> Vec<&'static str> {
+    GRAPH_FORMATS.to_vec()
+}
+
+impl TryFrom<&str> for GraphFormat {
+    type Error = String;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        match name {
+            "dot" | "graphviz" => Ok(GraphFormat::Dot),
+            "mermaid" => Ok(GraphFormat::Mermaid),
+            _ => Err(format!("Unknown graph format '{}' (expected one of {})",
+                             name, GRAPH_FORMATS.join(", ")))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GraphOptions {
+    // the links to draw, every one by default
+    pub links: Vec<String>,
+    // the edges reaching a node past this number are left out
+    pub max_nodes: Option<usize>
+}
+
+fn dot_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n"))
+}
+
+fn mermaid_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "#quot;").replace('\n', " "))
+}
+
+// the nodes in the order they appear and the edges between them
+struct Graph {
+    nodes: Vec<String>,
+    edges: Vec<(usize, String, usize)>,
+    left_out: usize
+}
+
+impl Graph {
+    fn new(df: &DataFrame, options: &GraphOptions) -> Result<Self, String> {
+        if df.nb_columns() != 3 {
+            return Err(format!("A graph is drawn from 3 columns (subject, link, goal), not {}", df.nb_columns()));
+        }
+        let mut graph = Graph { nodes: vec![], edges: vec![], left_out: 0 };
+        let mut index = HashMap::new();
+        for row in df.iter() {
+            let (subject, link, goal) = (&row[0], &row[1], &row[2]);
+            if !options.links.is_empty() && !options.links.contains(link) {
+                continue;
+            }
+            let mut new_nodes = [subject, goal].into_iter().filter(|x| !index.contains_key(*x)).collect::<Vec<_>>();
+            new_nodes.dedup();
+            if options.max_nodes.is_some_and(|max| graph.nodes.len() + new_nodes.len() > max) {
+                graph.left_out += 1;
+                continue;
+            }
+            let mut id = |node: &String| *index.entry(node.clone()).or_insert_with(|| {
+                graph.nodes.push(node.clone());
+                graph.nodes.len() - 1
+            });
+            let edge = (id(subject), link.clone(), id(goal));
+            if !graph.edges.contains(&edge) {
+                graph.edges.push(edge);
+            }
+        }
+        Ok(graph)
+    }
+
+    fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph predicat {\n");
+        if self.left_out > 0 {
+            dot += &format!("    // edges left out: {}\n", self.left_out);
+        }
+        for (subject, link, goal) in &self.edges {
+            dot += &format!("    {} -> {} [label={}];\n",
+                            dot_string(&self.nodes[*subject]), dot_string(&self.nodes[*goal]), dot_string(link));
+        }
+        dot + "}\n"
+    }
+
+    // the nodes get an id as Mermaid doesn't accept every name
+    fn to_mermaid(&self) -> String {
+        let mut mermaid = String::from("graph LR\n");
+        if self.left_out > 0 {
+            mermaid += &format!("    %% edges left out: {}\n", self.left_out);
+        }
+        for (id, node) in self.nodes.iter().enumerate() {
+            mermaid += &format!("    n{}[{}]\n", id, mermaid_string(node));
+        }
+        for (subject, link, goal) in &self.edges {
+            mermaid += &format!("    n{} -->|{}| n{}\n", subject, mermaid_string(link), goal);
+        }
+        mermaid
+    }
+}
+
+impl DataFrame {
+    pub fn to_graph(&self, format: GraphFormat, options: &GraphOptions) -> Result<String, String> {
+        let graph = Graph::new(self, options)?;
+        Ok(match format {
+            GraphFormat::Dot => graph.to_dot(),
+            GraphFormat::Mermaid => graph.to_mermaid()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dataframe() -> DataFrame {
+        let rows = [["pierre", "ami", "julie"], ["julie", "ami", "pierre"],
+                    ["julie", "dit", "\"salut\""], ["pierre", "ami", "anne"]];
+        DataFrame::from_rows(&["$A", "$B", "$C"],
+                             rows.iter().map(|row| row.iter().map(|x| x.to_string()).collect()).collect())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_to_graph() {
+        let options = GraphOptions { links: vec!["ami".to_string()], max_nodes: Some(2) };
+        assert_eq!(dataframe().to_graph(GraphFormat::Dot, &options),
+                   Ok("digraph predicat {\n    // edges left out: 1\n    \
+                       \"pierre\" -> \"julie\" [label=\"ami\"];\n    \
+                       \"julie\" -> \"pierre\" [label=\"ami\"];\n}\n".to_string()));
+        assert_eq!(dataframe().to_graph(GraphFormat::Mermaid, &GraphOptions { links: vec!["dit".to_string()], max_nodes: None }),
+                   Ok("graph LR\n    n0[\"julie\"]\n    n1[\"#quot;salut#quot;\"]\n    n0 -->|\"dit\"| n1\n".to_string()));
+        assert!(DataFrame::with_schema(&["$A"]).to_graph(GraphFormat::Dot, &GraphOptions::default()).is_err());
+    }
+}
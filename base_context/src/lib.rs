@@ -1,2 +1,5 @@
 pub mod simple_context;
 pub mod context_traits;
+pub mod output;
+pub mod stream;
+pub mod graph;
@@ -0,0 +1,191 @@
+// Serialization of a DataFrame for a terminal, for other programs or for a report,
+// the columns being written in the order of the schema.
+
+use cli_table::{Style, Table};
+use crate::context_traits::Var;
+use crate::simple_context::DataFrame;
+use crate::stream::RowStream;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+    Tsv,
+    Markdown
+}
+
+static OUTPUT_FORMATS: [&str; 5] = ["table", "json", "csv", "tsv", "markdown"];
+
+pub fn output_format_names() -> Vec<&'static str> {
+    OUTPUT_FORMATS.to_vec()
+}
+
+impl TryFrom<&str> for OutputFormat {
+    type Error = String;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        match name {
+            "table" => Ok(OutputFormat::Table),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "tsv" => Ok(OutputFormat::Tsv),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            _ => Err(format!("Unknown output format '{}' (expected one of {})",
+                             name, OUTPUT_FORMATS.join(", ")))
+        }
+    }
+}
+
+fn title(variables: &[Var]) -> Vec<String> {
+    variables.iter().map(Var::without_dollar).collect()
+}
+
+fn quote(cell: &str, delimiter: char) -> String {
+    match cell.contains([delimiter, '"', '\n', '\r']) {
+        true => format!("\"{}\"", cell.replace('"', "\"\"")),
+        false => cell.to_string()
+    }
+}
+
+fn json_string(s: &str) -> String {
+    serde_json::to_string(s).unwrap_or_default()
+}
+
+fn delimited_line(row: &[String], delimiter: char) -> String {
+    row.iter().map(|cell| quote(cell, delimiter))
+        .collect::<Vec<_>>().join(&delimiter.to_string()) + "\n"
+}
+
+fn markdown_line(row: &[String]) -> String {
+    format!("| {} |\n", row.iter()
+            .map(|cell| cell.replace('|', "\\|").replace('\n', " "))
+            .collect::<Vec<_>>().join(" | "))
+}
+
+// an object whose keys keep the order of the columns
+fn json_object(title: &[String], row: &[String]) -> String {
+    format!("{{{}}}", title.iter().zip(row)
+            .map(|(column, cell)| format!("{}: {}", json_string(column), json_string(cell)))
+            .collect::<Vec<_>>().join(", "))
+}
+
+// every format but the table, which needs all the rows to size its columns,
+// gives back the number of rows written
+fn write_rows(title: &[String], rows: impl Iterator<Item = Result<Vec<String>, String>>,
+              format: OutputFormat, out: &mut dyn Write) -> Result<usize, String> {
+    let io = |e: std::io::Error| e.to_string();
+    match format {
+        OutputFormat::Csv | OutputFormat::Tsv if !title.is_empty() =>
+            out.write_all(delimited_line(title, delimiter(format)).as_bytes()).map_err(io)?,
+        OutputFormat::Markdown if !title.is_empty() =>
+            out.write_all((markdown_line(title) + &markdown_line(&vec!["---".to_string(); title.len()])).as_bytes())
+                .map_err(io)?,
+        _ => ()
+    }
+    let mut count = 0;
+    for row in rows {
+        let row = row?;
+        let line = match format {
+            OutputFormat::Json => format!("{}  {}", if count == 0 { "[\n" } else { ",\n" }, json_object(title, &row)),
+            OutputFormat::Markdown => markdown_line(&row),
+            _ => delimited_line(&row, delimiter(format))
+        };
+        out.write_all(line.as_bytes()).map_err(io)?;
+        count += 1;
+    }
+    match (format, count) {
+        (OutputFormat::Json, 0) => out.write_all(b"[]\n").map_err(io)?,
+        (OutputFormat::Json, _) => out.write_all(b"\n]\n").map_err(io)?,
+        _ => ()
+    }
+    out.flush().map_err(io)?;
+    Ok(count)
+}
+
+fn delimiter(format: OutputFormat) -> char {
+    match format {
+        OutputFormat::Tsv => '\t',
+        _ => ','
+    }
+}
+
+// the rows are written as they come, but for a table
+pub fn write_stream(stream: RowStream, format: OutputFormat, out: &mut dyn Write) -> Result<usize, String> {
+    match format {
+        OutputFormat::Table => {
+            let df = stream.into_dataframe()?;
+            out.write_all(df.render(format).as_bytes()).map_err(|e| e.to_string())?;
+            Ok(df.len())
+        },
+        _ => write_rows(&title(&stream.get_variables()), stream, format, out)
+    }
+}
+
+impl DataFrame {
+    pub fn to_table(&self) -> String {
+        match self.len() {
+            0 => "EMPTY".to_string(),
+            _ => self.iter().collect::<Vec<_>>()
+                .table().title(title(&self.get_variables())).bold(true)
+                .display().map(|table| table.to_string())
+                .unwrap_or_default()
+        }
+    }
+
+    fn written(&self, format: OutputFormat) -> String {
+        let mut out = vec![];
+        let _ = write_rows(&title(&self.get_variables()), self.iter().map(Ok), format, &mut out);
+        String::from_utf8(out).unwrap_or_default()
+    }
+
+    pub fn to_json(&self) -> String {
+        self.written(OutputFormat::Json).trim_end().to_string()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        self.written(OutputFormat::Markdown)
+    }
+
+    pub fn render(&self, format: OutputFormat) -> String {
+        match format {
+            OutputFormat::Table => self.to_table() + "\n",
+            _ => self.written(format)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dataframe() -> DataFrame {
+        DataFrame::try_from(vec![
+            ("name".to_string(), "pierre".to_string()), ("note".to_string(), "a, \"b\"".to_string()),
+            ("name".to_string(), "julie".to_string()), ("note".to_string(), "x|y".to_string())])
+            .unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        let df = dataframe();
+        assert_eq!(df.render(OutputFormat::Csv), "name,note\npierre,\"a, \"\"b\"\"\"\njulie,x|y\n");
+        assert_eq!(df.render(OutputFormat::Tsv), "name\tnote\npierre\t\"a, \"\"b\"\"\"\njulie\tx|y\n");
+        assert_eq!(df.render(OutputFormat::Json),
+                   "[\n  {\"name\": \"pierre\", \"note\": \"a, \\\"b\\\"\"},\n  {\"name\": \"julie\", \"note\": \"x|y\"}\n]\n");
+        assert_eq!(df.render(OutputFormat::Markdown),
+                   "| name | note |\n| --- | --- |\n| pierre | a, \"b\" |\n| julie | x\\|y |\n");
+    }
+
+    #[test]
+    fn test_render_empty() {
+        let df = DataFrame::new();
+        assert_eq!(df.render(OutputFormat::Table), "EMPTY\n");
+        assert_eq!(df.render(OutputFormat::Json), "[]\n");
+        assert_eq!(df.render(OutputFormat::Csv), "");
+        assert_eq!(OutputFormat::try_from("md"), Ok(OutputFormat::Markdown));
+        assert!(OutputFormat::try_from("xml").is_err());
+    }
+}
@@ -1,11 +1,50 @@
-use cli_table::{Style, Table};
 use crate::context_traits::{Context, Var};
+use crate::output::OutputFormat;
 use itertools::*;
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DataFrameError {
-    InexistentColumnIn(String, Vec<String>)
+    InexistentColumnIn(String, Vec<String>),
+    DuplicateColumn(String),
+    WrongRowLength { expected: usize, found: usize },
+    SchemaMismatch(Vec<String>, Vec<String>)
+}
+
+impl fmt::Display for DataFrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataFrameError::InexistentColumnIn(column, columns) =>
+                write!(f, "The column '{}' isn't in ({})", column, columns.join(", ")),
+            DataFrameError::DuplicateColumn(column) =>
+                write!(f, "The column '{}' appears twice", column),
+            DataFrameError::WrongRowLength { expected, found } =>
+                write!(f, "A row has {} cells instead of {}", found, expected),
+            DataFrameError::SchemaMismatch(left, right) =>
+                write!(f, "The columns ({}) differ from ({})", left.join(", "), right.join(", "))
+        }
+    }
+}
+
+// a row seen through the schema of its dataframe
+#[derive(Debug, Clone, Copy)]
+pub struct Row<'a> {
+    schema: &'a [Var],
+    values: &'a [String]
+}
+
+impl<'a> Row<'a> {
+    pub fn get(&self, column: &str) -> Option<&'a str> {
+        let var = Var::new(column);
+        self.schema.iter().position(|x| *x == var)
+            .map(|index| &self.values[index][..])
+    }
+
+    pub fn values(&self) -> &'a [String] {
+        self.values
+    }
 }
 
 trait Adder {
@@ -14,15 +53,7 @@ trait Adder {
 
 impl Adder for HashMap<String, Vec<String>> {
     fn add(&mut self, k: &str, v: &str) {
-        if self.contains_key(k) {
-            let val = self.get(k).unwrap().iter()
-                        .chain([v.to_string()].iter())
-                        .cloned()
-                        .collect::<Vec<_>>();
-            self.insert(k.to_string(), val);
-        } else {
-            self.insert(k.to_string(), vec![v.to_string()]);
-        }
+        self.entry(k.to_string()).or_default().push(v.to_string());
     }
 }
 
@@ -34,95 +65,99 @@ fn create_hashmap(item: Vec<(String,String)>) -> HashMap<String, Vec<String>> {
 
 #[derive(Eq, PartialEq, Debug, Clone, Default)]
 pub struct DataFrame {
+    // the columns in the order they were asked for
+    schema: Vec<Var>,
     cells: HashMap<String, Vec<String>>,
-    rows: i32,
-    columns: i8
+    rows: usize
 }
 
 impl DataFrame {
     pub fn len(&self) -> usize {
-       self.cells.iter()
-            .next().unwrap_or((&"".to_string(), &vec![])).1.len()
+        self.rows
+    }
+
+    // no rows, whether the columns are known or not
+    pub fn is_empty(&self) -> bool {
+        self.rows == 0
     }
 
     pub fn empty(&self) -> bool {
-        self.rows == 0 && self.columns == 0
+        self.rows == 0 && self.schema.is_empty()
     }
 
     pub fn new() -> Self {
         DataFrame {
+            schema: vec![],
             cells: HashMap::new(),
-            rows: 0,
-            columns: 0
+            rows: 0
         }
     }
 
-    fn body(t: &[(String, String)]) -> Option<Self> {
-        let df = Self::to_dataframe(t);
-        match Self::check(&df) {
-            true => Some(df.clone()),
-            false => None
+    // a dataframe without rows whose columns are known
+    pub fn with_schema(columns: &[&str]) -> Self {
+        let schema = columns.iter().map(|x| Var::new(x)).unique().collect::<Vec<_>>();
+        DataFrame {
+            cells: schema.iter().map(|var| (var.0.clone(), vec![])).collect(),
+            schema,
+            rows: 0
         }
-    } 
-
-    fn to_dataframe(t: &[(String, String)]) -> DataFrame {
-        let df = DataFrame { 
-            cells: create_hashmap(t.to_vec()),
-            rows: 0, 
-            columns: 0
-        };
+    }
 
-        DataFrame {
-            rows: Self::nb_rows(&df) as i32,
-            columns: Self::nb_columns(&df) as i8,
-            ..df
+    pub fn from_rows(columns: &[&str], rows: Vec<Vec<String>>) -> Result<Self, DataFrameError> {
+        let mut df = Self::with_schema(columns);
+        if let Some(duplicate) = columns.iter().duplicates_by(|x| Var::new(x)).next() {
+            return Err(DataFrameError::DuplicateColumn(duplicate.to_string()));
         }
+        rows.into_iter().try_for_each(|row| df.append_row(row))?;
+        Ok(df)
     }
 
-    fn check(df: &DataFrame) -> bool {
-        if df.empty() {
-            true
-        } else {
-           let same_size = |x, y| if x == y { x } else { -1 };
-           let same = df.get_variables().iter()
-               .map(|var| df.get_values(var).unwrap().len() as i32)
-               .reduce(same_size)
-               .unwrap();
-           match same {
-               -1 => false,
-               _ => true
-           }
+    pub fn nb_columns(&self) -> usize {
+        self.schema.len()
+    }
+
+    pub fn append_row(&mut self, row: Vec<String>) -> Result<(), DataFrameError> {
+        if row.len() != self.schema.len() {
+            return Err(DataFrameError::WrongRowLength { expected: self.schema.len(), found: row.len() });
         }
+        self.schema.iter().zip(row)
+            .for_each(|(var, cell)| self.cells.entry(var.0.clone()).or_default().push(cell));
+        self.rows += 1;
+        Ok(())
     }
 
-    fn nb_rows(df: &DataFrame) -> usize {
-        if df.empty() {
-            return 0 as usize;
-        } else {
-            let variables = df.get_variables();
-            let first_column = variables.iter().next().unwrap();
-            df.get_values(&first_column.0).unwrap().len()
+    fn body(t: &[(String, String)]) -> Option<Self> {
+        let df = Self::to_dataframe(t);
+        match Self::check(&df) {
+            true => Some(df),
+            false => None
         }
+    } 
+
+    fn to_dataframe(t: &[(String, String)]) -> DataFrame {
+        let schema = t.iter().map(|(k, _)| Var::new(k)).unique().collect::<Vec<_>>();
+        let cells = create_hashmap(t.to_vec());
+        let rows = schema.first()
+            .and_then(|var| cells.get(&var.0))
+            .map(Vec::len).unwrap_or(0);
+        DataFrame { schema, cells, rows }
     }
 
-    fn nb_columns(df: &DataFrame) -> usize {
-        let variables = df.get_variables();
-        variables.len()
+    fn check(df: &DataFrame) -> bool {
+        df.schema.iter()
+            .all(|var| df.cells.get(&var.0).map(Vec::len) == Some(df.rows))
     }
 
-    fn iter(&self) -> DataFrameIterator {
+    // the rows in order, each giving its cells in the order of the schema
+    pub fn iter(&self) -> DataFrameIterator<'_> {
         DataFrameIterator {
             dataframe: self,
             index: 0,
         }
     }
 
-    fn get_variables(&self) -> Vec<Var>{
-        self.cells.keys()
-            .map(|var| {
-                    Var::new(&var.clone())
-                })
-            .sorted().unique().collect()
+    pub fn get_variables(&self) -> Vec<Var>{
+        self.schema.clone()
     }
 
     pub fn get_values(&self, key: &str) -> Result<Vec<String>, DataFrameError> {
@@ -141,28 +176,117 @@ impl DataFrame {
         if res.len() < columns.len() {
             None
         } else {
-            Some((0..(res[0].len()))
+            Some((0..self.rows)
                 .map(|index| res.iter().map(|x| x[index].clone()).collect::<Vec<_>>())
                 .collect())
         }
     }
 
-    fn add_column(&mut self, name: &str, elements: &[&str]) {
-        self.cells.insert(name.to_string(),
+    // a column replacing the one of the same name, as long as the first one
+    pub fn add_column(&mut self, name: &str, elements: &[&str]) -> Result<(), DataFrameError> {
+        let var = Var::new(name);
+        let replaced = self.schema.contains(&var);
+        if elements.len() != self.rows && self.schema.len() > usize::from(replaced) {
+            return Err(DataFrameError::WrongRowLength { expected: self.rows, found: elements.len() });
+        }
+        if !replaced {
+            self.schema.push(var.clone());
+        }
+        self.cells.insert(var.0,
                           elements.iter().map(|x| x.to_string()).collect());
-        self.rows = elements.len() as i32;
-        self.columns = self.columns + 1;
+        self.rows = elements.len();
+        Ok(())
+    }
+
+    fn index_of(&self, column: &str) -> Result<usize, DataFrameError> {
+        let var = Var::new(column);
+        self.schema.iter().position(|x| *x == var)
+            .ok_or(DataFrameError::InexistentColumnIn(
+                    var.0, self.schema.iter().map(|Var(x)| x.to_string()).collect()))
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.schema.iter().map(|var| &var[..]).collect()
+    }
+
+    // the same columns with other rows
+    fn with_rows(&self, rows: Vec<Vec<String>>) -> Result<DataFrame, DataFrameError> {
+        DataFrame::from_rows(&self.names(), rows)
+    }
+
+    pub fn select(&self, columns: &[&str]) -> Result<DataFrame, DataFrameError> {
+        let indexes = columns.iter()
+            .map(|column| self.index_of(column))
+            .collect::<Result<Vec<_>, _>>()?;
+        DataFrame::from_rows(columns, self.iter()
+                             .map(|row| indexes.iter().map(|index| row[*index].clone()).collect())
+                             .collect())
+    }
+
+    pub fn filter(&self, predicate: impl Fn(&Row) -> bool) -> Result<DataFrame, DataFrameError> {
+        self.with_rows(self.iter()
+                       .filter(|values| predicate(&Row { schema: &self.schema, values }))
+                       .collect())
+    }
+
+    // a stable sort
+    pub fn sort_by(&self, mut compare: impl FnMut(&Row, &Row) -> Ordering) -> Result<DataFrame, DataFrameError> {
+        let mut rows = self.iter().collect::<Vec<_>>();
+        rows.sort_by(|a, b| compare(&Row { schema: &self.schema, values: a },
+                                    &Row { schema: &self.schema, values: b }));
+        self.with_rows(rows)
+    }
+
+    // the first occurrence of every row
+    pub fn distinct(&self) -> Result<DataFrame, DataFrameError> {
+        self.with_rows(self.iter().unique().collect())
+    }
+
+    pub fn rename(&self, from: &str, to: &str) -> Result<DataFrame, DataFrameError> {
+        let index = self.index_of(from)?;
+        let mut columns = self.names();
+        columns[index] = to;
+        DataFrame::from_rows(&columns, self.iter().collect())
+    }
+
+    // the rows of both dataframes, in the columns order of the first one
+    pub fn union(&self, df: &DataFrame) -> Result<DataFrame, DataFrameError> {
+        if self.schema.iter().sorted().ne(df.schema.iter().sorted()) {
+            return Err(DataFrameError::SchemaMismatch(
+                    self.names().iter().map(|x| x.to_string()).collect(),
+                    df.names().iter().map(|x| x.to_string()).collect()));
+        }
+        let df = df.select(&self.names())?;
+        self.with_rows(self.iter().chain(df.iter()).collect())
     }
 
     fn is_in_dataframe(&self, key: String) -> bool {
         self.get_variables().iter()
             //.map(Var::without_dollar)
-            .any(|x| &x[..] == key)
+            .any(|x| x[..] == key)
     }
 
-    fn join(&self, df: Self) -> Self {
-       //cells: HashMap<String, Vec<String>>,
-       todo!();
+    // the natural join on the shared columns, a cartesian product when there are none
+    pub fn join(&self, df: &DataFrame) -> Result<DataFrame, DataFrameError> {
+        let shared = self.schema.iter()
+            .filter_map(|var| df.schema.iter().position(|x| x == var))
+            .collect::<Vec<_>>();
+        let key = |row: &[String], indexes: &[usize]| indexes.iter().map(|index| row[*index].clone()).collect::<Vec<_>>();
+        let left_key = self.schema.iter().enumerate()
+            .filter(|(_, var)| df.schema.contains(var))
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        let extra = (0..df.schema.len()).filter(|index| !shared.contains(index)).collect::<Vec<_>>();
+        let mut matches: HashMap<Vec<String>, Vec<Vec<String>>> = HashMap::new();
+        df.iter().for_each(|row| matches.entry(key(&row, &shared)).or_default().push(key(&row, &extra)));
+        let columns = self.names().into_iter()
+            .chain(extra.iter().map(|index| &df.schema[*index][..]))
+            .collect::<Vec<_>>();
+        let rows = self.iter()
+            .flat_map(|row| matches.get(&key(&row, &left_key)).cloned().unwrap_or_default().into_iter()
+                      .map(move |extra| row.iter().cloned().chain(extra).collect()))
+            .collect();
+        DataFrame::from_rows(&columns, rows)
     }
 }
 
@@ -183,7 +307,7 @@ impl TryFrom<Vec<Vec<String>>> for DataFrame {
     }
 }
 
-struct DataFrameIterator<'a> {
+pub struct DataFrameIterator<'a> {
     dataframe: &'a DataFrame,
     index: usize,
 }
@@ -193,19 +317,27 @@ impl<'a> Iterator for DataFrameIterator<'a> {
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.index < self.dataframe.len() {
-            //let result = &self.dataframe.cells.get(self.index).unwrap();
             // get the ith index of the dataframe for each column
-            let result = self.dataframe.get_variables()
-                .iter().map(|var| self.dataframe.cells.get(&var.0).unwrap()[self.index].clone())
+            let result = self.dataframe.schema
+                .iter().map(|var| self.dataframe.cells[&var.0][self.index].clone())
                 .collect::<Vec<_>>();
             self.index += 1;
-            Some(result.clone())
+            Some(result)
         } else {
             None
         }
     }
 }
 
+impl<'a> IntoIterator for &'a DataFrame {
+    type Item = Vec<String>;
+    type IntoIter = DataFrameIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
 #[derive(Eq, PartialEq, Debug, Clone, Default)]
 pub struct SimpleContext {
     pub tab: DataFrame,
@@ -230,36 +362,24 @@ impl SimpleContext {
         self.tab.len()
     }
 
-pub fn display(&self) {
-    // TODO : display error if any and call revert back in the back
-    match self.dataframe_len() {
-        x if x > 0 => {
-            let variables = self.get_variables().iter()
-                .map(Var::without_dollar).collect::<Vec<String>>();
-            let body = self.get_variables().iter()
-                .map(|x| self.get_values(&x.without_dollar()).unwrap_or(vec![]))
-                .collect::<Vec<_>>();
-            let table = (0..self.dataframe_len()).map(|x| get_line(x, &body))
-                .table()
-                .title(variables)
-                .bold(true)
-                .display()
-                .unwrap();
-                println!("{}", table);
+    pub fn display(&self) {
+        self.display_as(OutputFormat::Table)
+    }
+
+    pub fn display_as(&self, format: OutputFormat) {
+        // TODO : call revert back in the back
+        if self.has_error() {
+            self.log.iter().for_each(|error| eprintln!("error: {}", error));
+            return;
         }
-        _ => println!("EMPTY")
+        print!("{}", self.tab.render(format));
     }
-}
 
     pub fn get_tab(&self) -> DataFrame {
         self.tab.clone()
     }
 }
 
-fn get_line(num: usize, body: &[Vec<String>]) -> Vec<String> {
-    body.iter().map(|x| x.iter().nth(num).unwrap_or(&"".to_string()).clone()).collect()
-}
-
 impl TryFrom<Vec<(String, String)>> for SimpleContext {
     type Error = String;
 
@@ -314,7 +434,9 @@ impl Context for SimpleContext {
     }
 
     fn add_column(&mut self, name: &str, elements: &[&str]) -> SimpleContext{
-        self.tab.add_column(name, elements);
+        if let Err(error) = self.tab.add_column(name, elements) {
+            self.log.push(error.to_string());
+        }
         self.clone()
     }
 
@@ -326,30 +448,19 @@ impl Context for SimpleContext {
         self.tab.len()
     }
 
+    // the natural join of the tables, a context without columns leaving the other table as is
     fn join(&self, c2: SimpleContext) -> SimpleContext {
-        let vec_tab = self.get_tab()
-                    .iter()
-                    .chain(c2.get_tab().iter())
-                    .map(|x| x.clone())
-                    .collect::<Vec<_>>();
-        let vec_cmds = self.cmds
-                    .iter()
-                    .chain(c2.cmds.iter())
-                    .map(|x| x.clone())
-                    .collect::<Vec<_>>();
-        let vec_log = self.log
-                    .iter()
-                    .chain(c2.log.iter())
-                    .map(|x| x.clone())
-                    .collect::<Vec<_>>();
-
-        SimpleContext {
-            tab: vec_tab.try_into().unwrap(),
-            cmds: vec_cmds,
-            log: vec_log,
+        let mut log = self.log.iter().chain(c2.log.iter()).cloned().collect::<Vec<_>>();
+        let tab = match (self.tab.nb_columns(), c2.tab.nb_columns()) {
+            (0, _) => c2.tab.clone(),
+            (_, 0) => self.tab.clone(),
+            _ => self.tab.join(&c2.tab).unwrap_or_else(|error| { log.push(error.to_string()); DataFrame::new() })
         };
-
-        todo!();
+        SimpleContext {
+            tab,
+            cmds: self.cmds.iter().chain(c2.cmds.iter()).cloned().collect(),
+            log
+        }
     }
     
     fn is_empty(&self) -> bool {
@@ -400,6 +511,66 @@ mod tests {
         assert_eq!(
             DataFrame::check(&df),
             true);
+        assert_eq!(df.len(), 2);
+    }
+
+    #[test]
+    fn test_dataframe_keeps_column_and_row_order() {
+        let df = DataFrame::from_rows(&["$Z", "$A"], vec![vec!["1".to_string(), "2".to_string()],
+                                                          vec!["3".to_string(), "4".to_string()]])
+            .unwrap();
+        assert_eq!(df.get_variables(), vec![Var::new("$Z"), Var::new("$A")]);
+        assert_eq!(df.iter().collect::<Vec<_>>(),
+                   vec![vec!["1".to_string(), "2".to_string()], vec!["3".to_string(), "4".to_string()]]);
+        let empty = DataFrame::with_schema(&["Z", "A"]);
+        assert_eq!((empty.len(), empty.get_variables().len()), (0, 2));
+        assert!(DataFrame::from_rows(&["$A"], vec![vec![]]).is_err());
+    }
+
+    fn strings(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter().map(|row| row.iter().map(|x| x.to_string()).collect()).collect()
+    }
+
+    #[test]
+    fn test_dataframe_api() {
+        let df = DataFrame::from_rows(&["$name", "$age"], strings(&[&["pierre", "24"], &["julie", "31"], &["pierre", "24"]]))
+            .unwrap();
+        assert_eq!(df.distinct().unwrap().iter().collect::<Vec<_>>(), strings(&[&["pierre", "24"], &["julie", "31"]]));
+        assert_eq!(df.filter(|row| row.get("age") == Some("31")).unwrap().len(), 1);
+        assert!(df.filter(|_| false).unwrap().is_empty());
+        assert_eq!(df.sort_by(|a, b| a.get("$name").cmp(&b.get("$name"))).unwrap().get_values("$name").unwrap(),
+                   vec!["julie", "pierre", "pierre"]);
+        assert_eq!(df.select(&["$age"]).unwrap().get_variables(), vec![Var::new("$age")]);
+        assert!(df.select(&["$city"]).is_err());
+        assert_eq!(df.rename("$age", "$years").unwrap().get_variables(), vec![Var::new("$name"), Var::new("$years")]);
+        assert_eq!(df.rename("$age", "$name"), Err(DataFrameError::DuplicateColumn("$name".to_string())));
+        let other = DataFrame::from_rows(&["$age", "$name"], strings(&[&["40", "paul"]])).unwrap();
+        assert_eq!(df.union(&other).unwrap().iter().last(), Some(strings(&[&["paul", "40"]]).remove(0)));
+        let mut df = df;
+        assert_eq!(df.append_row(strings(&[&["paul"]]).remove(0)),
+                   Err(DataFrameError::WrongRowLength { expected: 2, found: 1 }));
+        assert_eq!(df.add_column("$city", &["paris"]), Err(DataFrameError::WrongRowLength { expected: 3, found: 1 }));
+    }
+
+    #[test]
+    fn test_dataframe_join() {
+        let people = DataFrame::from_rows(&["$name", "$company"], strings(&[&["pierre", "acme"], &["julie", "corp"], &["paul", "none"]]))
+            .unwrap();
+        let companies = DataFrame::from_rows(&["$company", "$city"], strings(&[&["corp", "lyon"], &["acme", "paris"], &["acme", "nice"]]))
+            .unwrap();
+        let joined = people.join(&companies).unwrap();
+        assert_eq!(joined.get_variables(), vec![Var::new("$name"), Var::new("$company"), Var::new("$city")]);
+        assert_eq!(joined.iter().collect::<Vec<_>>(),
+                   strings(&[&["pierre", "acme", "paris"], &["pierre", "acme", "nice"], &["julie", "corp", "lyon"]]));
+        assert_eq!(people.select(&["$name"]).unwrap().join(&companies.select(&["$city"]).unwrap()).unwrap().len(), 9);
+    }
+
+    #[test]
+    fn test_context_join() {
+        let rows = SimpleContext::from(DataFrame::from_rows(&["$A"], strings(&[&["pierre"]])).unwrap());
+        let failed = SimpleContext { log: vec!["The rule couldn't be stored".to_string()], ..SimpleContext::default() };
+        let joined = SimpleContext::join_contexts(failed, rows.clone());
+        assert_eq!((joined.get_tab(), joined.log.len()), (rows.get_tab(), 1));
     }
 
 }
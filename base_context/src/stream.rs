@@ -0,0 +1,67 @@
+// The rows of a result read one at a time, a DataFrame being built only when asked for.
+
+use crate::context_traits::Var;
+use crate::simple_context::DataFrame;
+
+pub struct RowStream<'a> {
+    schema: Vec<Var>,
+    rows: Box<dyn Iterator<Item = Result<Vec<String>, String>> + 'a>
+}
+
+impl<'a> RowStream<'a> {
+    pub fn new(columns: &[&str], rows: impl Iterator<Item = Result<Vec<String>, String>> + 'a) -> Self {
+        RowStream {
+            schema: columns.iter().map(|x| Var::new(x)).collect(),
+            rows: Box::new(rows)
+        }
+    }
+
+    pub fn get_variables(&self) -> Vec<Var> {
+        self.schema.clone()
+    }
+
+    pub fn into_dataframe(self) -> Result<DataFrame, String> {
+        let columns = self.schema.iter().map(|var| &var[..]).collect::<Vec<_>>();
+        let mut df = DataFrame::with_schema(&columns);
+        for row in self.rows {
+            df.append_row(row?).map_err(|e| e.to_string())?;
+        }
+        Ok(df)
+    }
+}
+
+impl Iterator for RowStream<'_> {
+    type Item = Result<Vec<String>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rows.next()
+    }
+}
+
+impl From<DataFrame> for RowStream<'static> {
+    fn from(df: DataFrame) -> Self {
+        let rows = df.iter().collect::<Vec<_>>();
+        RowStream {
+            schema: df.get_variables(),
+            rows: Box::new(rows.into_iter().map(Ok))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_is_lazy() {
+        let mut read = 0;
+        let stream = RowStream::new(&["$n"], (0..).map(|x: usize| Ok(vec![x.to_string()])))
+            .inspect(|_| read += 1)
+            .take(3)
+            .collect::<Result<Vec<_>, _>>();
+        assert_eq!(stream.map(|rows| rows.len()), Ok(3));
+        assert_eq!(read, 3);
+        let failing = RowStream::new(&["$n"], vec![Ok(vec!["1".to_string()]), Err("lost".to_string())].into_iter());
+        assert_eq!(failing.into_dataframe(), Err("lost".to_string()));
+    }
+}
@@ -0,0 +1,160 @@
+// The crates of a local Cargo workspace and their dependencies, from the manifests and the
+// Cargo.lock, without any network:
+//   knowledge is_a workspace_member     knowledge version 0.1.0
+//   knowledge depends_on parser         parser uses_crate nom
+//   nom is_a crate                      nom version 7.1.1
+// A dependency of the workspace is `depends_on`, any other one `uses_crate`, the dev and build
+// dependencies having the `dev_` and `build_` prefixes.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use itertools::Itertools;
+use toml::{Table, Value};
+use crate::rdf::{Triple, triple};
+
+fn read_manifest(path: &Path) -> Result<Table, String> {
+    fs::read_to_string(path)
+        .map_err(|e| format!("'{}' can't be read ({})", path.display(), e))?
+        .parse::<Table>()
+        .map_err(|e| format!("'{}' is malformed ({})", path.display(), e))
+}
+
+fn table<'a>(manifest: &'a Table, key: &str) -> Option<&'a Table> {
+    manifest.get(key).and_then(Value::as_table)
+}
+
+// the directories of the members, a member ending with `/*` standing for every directory in it
+fn member_directories(root: &Path, workspace: &Table) -> Vec<PathBuf> {
+    let excluded = workspace.get("exclude").and_then(Value::as_array).cloned().unwrap_or_default();
+    workspace.get("members").and_then(Value::as_array).cloned().unwrap_or_default().iter()
+        .filter_map(Value::as_str)
+        .flat_map(|member| match member.strip_suffix("/*") {
+            Some(parent) => fs::read_dir(root.join(parent))
+                .map(|entries| entries.flatten().map(|entry| entry.path()).sorted().collect::<Vec<_>>())
+                .unwrap_or_default(),
+            None => vec![root.join(member)]
+        })
+        .filter(|directory| directory.join("Cargo.toml").is_file())
+        .filter(|directory| !excluded.iter().filter_map(Value::as_str).any(|x| root.join(x) == *directory))
+        .collect()
+}
+
+// the name of a package, the version of the workspace being used when it is inherited
+fn package(manifest: &Table, workspace: Option<&Table>) -> Option<(String, Option<String>)> {
+    let package = table(manifest, "package")?;
+    let version = match package.get("version") {
+        Some(Value::String(version)) => Some(version.clone()),
+        Some(Value::Table(_)) => workspace.and_then(|x| table(x, "package"))
+            .and_then(|x| x.get("version")).and_then(Value::as_str).map(str::to_string),
+        _ => None
+    };
+    Some((package.get("name")?.as_str()?.to_string(), version))
+}
+
+// the dependencies of every kind, with their real name when they are renamed
+fn dependencies(manifest: &Table) -> Vec<(&'static str, String)> {
+    let targets = table(manifest, "target").into_iter()
+        .flat_map(|targets| targets.values().filter_map(Value::as_table));
+    std::iter::once(manifest).chain(targets)
+        .flat_map(|section| [("", "dependencies"), ("dev_", "dev-dependencies"), ("build_", "build-dependencies")]
+                  .into_iter()
+                  .filter_map(move |(prefix, key)| table(section, key).map(|deps| (prefix, deps))))
+        .flat_map(|(prefix, deps)| deps.iter().map(move |(name, dependency)| {
+            let name = dependency.get("package").and_then(Value::as_str).unwrap_or(name);
+            (prefix, name.to_string())
+        }))
+        .collect()
+}
+
+fn lock_facts(lock: &Table, members: &BTreeSet<String>) -> Vec<Triple> {
+    lock.get("package").and_then(Value::as_array).cloned().unwrap_or_default().iter()
+        .filter_map(Value::as_table)
+        .filter_map(|package| Some((package.get("name")?.as_str()?.to_string(), package)))
+        .flat_map(|(name, package)| {
+            let mut facts = vec![];
+            if !members.contains(&name) {
+                facts.push(triple(&name, "is_a", "crate"));
+                // a dependency is written `name`, `name version` or `name version (source)`
+                facts.extend(package.get("dependencies").and_then(Value::as_array).cloned().unwrap_or_default().iter()
+                             .filter_map(Value::as_str)
+                             .filter_map(|dependency| dependency.split_whitespace().next())
+                             .map(|dependency| triple(&name, "uses_crate", dependency)));
+            }
+            facts.extend(package.get("version").and_then(Value::as_str)
+                         .map(|version| triple(&name, "version", version)));
+            facts
+        })
+        .collect()
+}
+
+pub fn read(root: &Path) -> Result<Vec<Triple>, String> {
+    let root = match root.file_name().map(|x| x == "Cargo.toml") {
+        Some(true) => root.parent().unwrap_or(Path::new(".")),
+        _ => root
+    };
+    let manifest = read_manifest(&root.join("Cargo.toml"))?;
+    let workspace = table(&manifest, "workspace");
+    let manifests = std::iter::once(Ok(manifest.clone()))
+        .chain(workspace.map(|workspace| member_directories(root, workspace)).unwrap_or_default().iter()
+               .map(|directory| read_manifest(&directory.join("Cargo.toml"))))
+        .collect::<Result<Vec<_>, _>>()?;
+    let packages = manifests.iter()
+        .filter_map(|manifest| package(manifest, workspace).map(|package| (package, manifest)))
+        .collect::<Vec<_>>();
+    let members = packages.iter().map(|((name, _), _)| name.clone()).collect::<BTreeSet<_>>();
+    let mut facts = packages.iter()
+        .flat_map(|((name, version), manifest)| {
+            std::iter::once(triple(name, "is_a", "workspace_member"))
+                .chain(version.iter().map(|version| triple(name, "version", version)))
+                .chain(dependencies(manifest).into_iter().map(|(prefix, dependency)| {
+                    let link = match members.contains(&dependency) {
+                        true => "depends_on",
+                        false => "uses_crate"
+                    };
+                    triple(name, &format!("{}{}", prefix, link), &dependency)
+                }))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let lock = root.join("Cargo.lock");
+    if lock.is_file() {
+        facts.extend(lock_facts(&read_manifest(&lock)?, &members));
+    }
+    let mut seen = BTreeSet::new();
+    facts.retain(|x| seen.insert(x.clone()));
+    Ok(facts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_workspace() {
+        let root = std::env::temp_dir().join(format!("predicat_cargo_{}", std::process::id()));
+        fs::create_dir_all(root.join("crates/parser")).unwrap();
+        fs::create_dir_all(root.join("knowledge")).unwrap();
+        fs::write(root.join("Cargo.toml"),
+                  "[workspace]\nmembers = [\"knowledge\", \"crates/*\"]\n[workspace.package]\nversion = \"0.2.0\"\n").unwrap();
+        fs::write(root.join("knowledge/Cargo.toml"),
+                  "[package]\nname = \"knowledge\"\nversion.workspace = true\n\
+                   [dependencies]\nparser = { path = \"../crates/parser\" }\nsqlite = \"0.30\"\n\
+                   [dev-dependencies]\nserial = { package = \"serial_test\", version = \"2\" }\n").unwrap();
+        fs::write(root.join("crates/parser/Cargo.toml"),
+                  "[package]\nname = \"parser\"\nversion = \"0.1.0\"\n[dependencies]\nnom = \"7\"\n").unwrap();
+        fs::write(root.join("Cargo.lock"),
+                  "version = 3\n[[package]]\nname = \"nom\"\nversion = \"7.1.1\"\ndependencies = [\"memchr 2.5.0 (registry+x)\", \"minimal-lexical\"]\n\
+                   [[package]]\nname = \"parser\"\nversion = \"0.1.0\"\ndependencies = [\"nom\"]\n").unwrap();
+        let facts = read(&root.join("Cargo.toml"));
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(facts, Ok(vec![
+            triple("knowledge", "is_a", "workspace_member"), triple("knowledge", "version", "0.2.0"),
+            triple("knowledge", "depends_on", "parser"), triple("knowledge", "uses_crate", "sqlite"),
+            triple("knowledge", "dev_uses_crate", "serial_test"),
+            triple("parser", "is_a", "workspace_member"), triple("parser", "version", "0.1.0"),
+            triple("parser", "uses_crate", "nom"),
+            triple("nom", "is_a", "crate"), triple("nom", "uses_crate", "memchr"),
+            triple("nom", "uses_crate", "minimal-lexical"), triple("nom", "version", "7.1.1")]));
+    }
+}
@@ -0,0 +1,140 @@
+// Every row of a CSV becomes the facts of a template like `$name works_at $company`,
+// the columns being the variables of the template.
+
+use std::collections::HashSet;
+use base_context::simple_context::SimpleContext;
+use metaprogramming::substitute_variables;
+use parser::base_parser::PredicatAST;
+use parser::parse_command;
+use crate::rdf::Triple;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Header {
+    Detect,
+    Present,
+    Absent
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    pub header: Header
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions { delimiter: b',', header: Header::Detect }
+    }
+}
+
+// a column name usable as a variable of the template
+fn column_name(name: &str) -> String {
+    name.trim().chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+// a first row of distinct names giving every variable of the template is a header
+fn looks_like_header(first: &[String], variables: &[String]) -> bool {
+    let names = first.iter().map(|x| column_name(x)).collect::<Vec<_>>();
+    names.iter().collect::<HashSet<_>>().len() == names.len()
+        && variables.iter().all(|var| names.contains(var))
+}
+
+fn records(input: &str, delimiter: u8) -> Result<Vec<Vec<String>>, String> {
+    ::csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .from_reader(input.as_bytes())
+        .records()
+        .map(|record| record
+             .map(|record| record.iter().map(str::to_string).collect())
+             .map_err(|e| format!("the CSV is malformed ({})", e)))
+        .collect()
+}
+
+fn template_variables(template: &PredicatAST) -> Vec<String> {
+    match template {
+        PredicatAST::AddModifier(triplets) => triplets.iter()
+            .flat_map(|tri| {
+                let (s, l, g) = tri.to_tuple_with_variable();
+                [s, l, g]
+            })
+            .filter_map(|element| element.strip_prefix('$').map(str::to_string))
+            .collect(),
+        _ => vec![]
+    }
+}
+
+// `$name works_at $company and $name age $age`, the leading `add` being optional
+fn parse_template(template: &str) -> Result<PredicatAST, String> {
+    let command = match template.trim_start().starts_with("add ") {
+        true => template.trim().to_string(),
+        false => format!("add {}", template.trim())
+    };
+    match &parse_command(&command)[..] {
+        [ast @ PredicatAST::AddModifier(_)] => Ok(ast.clone()),
+        _ => Err(format!("the template '{}' isn't a list of triplets", template))
+    }
+}
+
+pub fn read(input: &str, template: &str, options: &CsvOptions) -> Result<Vec<Triple>, String> {
+    let template = parse_template(template)?;
+    let variables = template_variables(&template);
+    let rows = records(input, options.delimiter)?;
+    let (columns, rows) = match (rows.split_first(), options.header) {
+        (None, _) => return Ok(vec![]),
+        (Some((first, rest)), Header::Present) => (first.clone(), rest.to_vec()),
+        (Some((first, rest)), Header::Detect) if looks_like_header(first, &variables) => (first.clone(), rest.to_vec()),
+        (Some((first, _)), _) => ((1..=first.len()).map(|x| format!("c{}", x)).collect(), rows.clone())
+    };
+    let columns = columns.iter().map(|x| column_name(x)).collect::<Vec<_>>();
+    if let Some(missing) = variables.iter().find(|var| !columns.contains(var)) {
+        return Err(format!("the column '{}' isn't in the CSV (columns: {})", missing, columns.join(", ")));
+    }
+    if rows.is_empty() {
+        return Ok(vec![]);
+    }
+    let context = SimpleContext::try_from(rows.iter()
+        .flat_map(|row| columns.iter().enumerate()
+                  .map(|(index, column)| (column.clone(), row.get(index).cloned().unwrap_or_default())))
+        .collect::<Vec<_>>())?;
+    // the triplets with an empty cell are left out
+    Ok(substitute_variables(context)(template).unwrap_or_default().into_iter()
+        .flat_map(|ast| match ast {
+            PredicatAST::AddModifier(triplets) => triplets,
+            _ => vec![]
+        })
+        .map(|tri| tri.to_tuple())
+        .filter(|(s, l, g)| !s.is_empty() && !l.is_empty() && !g.is_empty())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdf::triple;
+
+    #[test]
+    fn test_read_with_header() {
+        let input = "name,company,age\npierre,acme,24\n\"dupont, anne\",\"corp \"\"x\"\"\",\n";
+        assert_eq!(
+            read(input, "$name works_at $company and $name age $age", &CsvOptions::default()),
+            Ok(vec![triple("pierre", "works_at", "acme"),
+                    triple("pierre", "age", "24"),
+                    triple("dupont, anne", "works_at", "corp \"x\"")]));
+        assert!(read(input, "$name lives_in $city", &CsvOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_read_without_header() {
+        let options = CsvOptions { delimiter: b';', header: Header::Detect };
+        assert_eq!(
+            read("pierre;julie\njulie;pierre\n", "$c1 ami $c2", &options),
+            Ok(vec![triple("pierre", "ami", "julie"), triple("julie", "ami", "pierre")]));
+        let options = CsvOptions { delimiter: b'\t', header: Header::Absent };
+        assert_eq!(
+            read("first name\tage\n", "add $c1 age $c2", &options),
+            Ok(vec![triple("first name", "age", "age")]));
+    }
+}
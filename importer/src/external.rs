@@ -0,0 +1,154 @@
+// An external importer is any executable called with `predicat import --importer <binary> [args]`.
+//
+// The protocol:
+// - the importer writes a statement per line on its standard output, either an N-Triples
+//   triple (`<urn:predicat:pierre> <urn:predicat:ami> "julie" .`) or a predicat command
+//   (`add pierre ami julie`, `delete ...`, `infer ...`), blank lines and lines starting
+//   with `#` being ignored
+// - the facts of the triples and of the `add` commands without variables are stored as they
+//   are read, the other commands are run in their order once every fact is stored
+// - its standard error goes to the one of predicat
+// - it must exit with the code 0, otherwise the import fails, the facts read so far being kept
+// - it is killed when it runs longer than the timeout, if one is given
+
+use std::io::{BufRead, BufReader, Lines};
+use std::process::{Child, ChildStdout, Command, ExitStatus, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use parser::base_parser::{PredicatAST, Triplet};
+use parser::parse_command;
+use crate::rdf::{self, RdfFormat, Triple};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Facts(Vec<Triple>),
+    Command(String)
+}
+
+// a line of the output of an importer
+pub fn parse_line(line: &str) -> Option<Result<Statement, String>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    if line.starts_with('<') || line.starts_with("_:") {
+        return Some(rdf::read(RdfFormat::NTriples, line).map(Statement::Facts));
+    }
+    Some(match &parse_command(line)[..] {
+        [PredicatAST::AddModifier(triplets)] if triplets.iter().all(|tri| matches!(tri, Triplet::Teee(..))) =>
+            Ok(Statement::Facts(triplets.iter().map(Triplet::to_tuple).collect())),
+        [PredicatAST::Empty] | [] => Err(format!("'{}' is neither a triple nor a command", line)),
+        _ => Ok(Statement::Command(line.to_string()))
+    })
+}
+
+// the statements of a running importer
+pub struct ExternalImport {
+    program: String,
+    lines: Lines<BufReader<ChildStdout>>,
+    number: usize,
+    watchdog: JoinHandle<Result<ExitStatus, String>>
+}
+
+// waits for the importer, killing it once the deadline is over
+fn watch(mut child: Child, program: String, timeout: Option<Duration>) -> Result<ExitStatus, String> {
+    let deadline = match timeout {
+        Some(timeout) => Instant::now() + timeout,
+        None => return child.wait().map_err(|e| e.to_string())
+    };
+    loop {
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => return Ok(status),
+            None if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("the importer '{}' was stopped after {} seconds", program,
+                                   timeout.unwrap_or_default().as_secs_f64()));
+            },
+            None => thread::sleep(Duration::from_millis(20))
+        }
+    }
+}
+
+pub fn spawn(program: &str, args: &[String], timeout: Option<Duration>) -> Result<ExternalImport, String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| format!("the importer '{}' can't be run ({})", program, e))?;
+    let stdout = child.stdout.take()
+        .ok_or(format!("the output of the importer '{}' can't be read", program))?;
+    let name = program.to_string();
+    Ok(ExternalImport {
+        program: program.to_string(),
+        lines: BufReader::new(stdout).lines(),
+        number: 0,
+        watchdog: thread::spawn(move || watch(child, name, timeout))
+    })
+}
+
+impl ExternalImport {
+    // waits for the end of the importer and checks how it went
+    pub fn finish(self) -> Result<(), String> {
+        let ExternalImport { program, lines, watchdog, .. } = self;
+        // what is left of the output is dropped aside so the importer doesn't block on it
+        thread::spawn(move || lines.for_each(drop));
+        let status = watchdog.join()
+            .map_err(|_| format!("the importer '{}' couldn't be waited for", program))??;
+        match status.code() {
+            Some(0) => Ok(()),
+            Some(code) => Err(format!("the importer '{}' exited with the code {}", program, code)),
+            None => Err(format!("the importer '{}' was stopped by a signal", program))
+        }
+    }
+}
+
+impl Iterator for ExternalImport {
+    type Item = Result<Statement, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(format!("the output of '{}' can't be read ({})", self.program, e)))
+            };
+            self.number += 1;
+            if let Some(statement) = parse_line(&line) {
+                return Some(statement.map_err(|e| format!("line {}: {}", self.number, e)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdf::triple;
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(parse_line("  # a comment"), None);
+        assert_eq!(parse_line("add pierre ami julie and julie ami pierre"),
+                   Some(Ok(Statement::Facts(vec![triple("pierre", "ami", "julie"), triple("julie", "ami", "pierre")]))));
+        assert_eq!(parse_line("<urn:predicat:pierre> <urn:predicat:age> \"24\" ."),
+                   Some(Ok(Statement::Facts(vec![triple("pierre", "age", "24")]))));
+        assert_eq!(parse_line("delete pierre ami julie"),
+                   Some(Ok(Statement::Command("delete pierre ami julie".to_string()))));
+        assert!(matches!(parse_line("pierre ami"), Some(Err(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_spawn() {
+        let script = |script: &str| vec!["-c".to_string(), script.to_string()];
+        let mut import = spawn("sh", &script("echo 'add a b c'; echo oops; exit 3"), None).unwrap();
+        assert_eq!(import.next(), Some(Ok(Statement::Facts(vec![triple("a", "b", "c")]))));
+        assert!(matches!(import.next(), Some(Err(e)) if e.starts_with("line 2")));
+        assert_eq!(import.finish(), Err("the importer 'sh' exited with the code 3".to_string()));
+        let import = spawn("sh", &script("exec sleep 5"), Some(Duration::from_millis(100))).unwrap();
+        assert!(import.finish().unwrap_err().contains("stopped after"));
+        assert!(spawn("/nonexistent/importer", &[], None).is_err());
+    }
+}
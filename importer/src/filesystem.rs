@@ -0,0 +1,136 @@
+// A directory tree becomes facts about its entries, the paths being relative to its root (`.`):
+//   src/main.rs is_a file        src is_a dir
+//   src/main.rs in_dir src       src in_dir .
+//   src/main.rs extension rs     src/main.rs size 1234
+// A glob without `/` matches the name of an entry, otherwise its whole path. The included
+// globs select the files, an excluded directory is left out with everything under it.
+
+use std::path::Path;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use walkdir::{DirEntry, WalkDir};
+use crate::rdf::Triple;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WalkOptions {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>
+}
+
+struct Patterns {
+    names: GlobSet,
+    paths: GlobSet
+}
+
+impl Patterns {
+    fn new(globs: &[String]) -> Result<Self, String> {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        for glob in globs {
+            let pattern = Glob::new(glob).map_err(|e| format!("the glob '{}' is malformed ({})", glob, e))?;
+            match glob.contains('/') {
+                true => paths.add(pattern),
+                false => names.add(pattern)
+            };
+        }
+        let build = |set: GlobSetBuilder| set.build().map_err(|e| e.to_string());
+        Ok(Patterns { names: build(names)?, paths: build(paths)? })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.paths.is_empty()
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        self.names.is_match(name) || self.paths.is_match(path)
+    }
+}
+
+fn relative(root: &Path, entry: &DirEntry) -> String {
+    let path = entry.path().strip_prefix(root).unwrap_or(entry.path());
+    match path.as_os_str().is_empty() {
+        true => ".".to_string(),
+        false => path.components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>().join("/")
+    }
+}
+
+fn parent(path: &str) -> String {
+    match path.rsplit_once('/') {
+        Some((parent, _)) => parent.to_string(),
+        None => ".".to_string()
+    }
+}
+
+// the facts of the tree, the entries which can't be read going to the warnings
+pub fn read(root: &Path, options: &WalkOptions, warn: &mut dyn FnMut(String)) -> Result<Vec<Triple>, String> {
+    if !root.is_dir() {
+        return Err(format!("'{}' isn't a directory", root.display()));
+    }
+    let include = Patterns::new(&options.include)?;
+    let exclude = Patterns::new(&options.exclude)?;
+    let walker = WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !exclude.matches(&relative(root, entry)));
+    let mut triples = vec![];
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => { warn(e.to_string()); continue }
+        };
+        let path = relative(root, &entry);
+        let kind = entry.file_type();
+        if !kind.is_dir() && !include.is_empty() && !include.matches(&path) {
+            continue;
+        }
+        let fact = |link: &str, goal: String| (path.clone(), link.to_string(), goal);
+        triples.push(fact("is_a", match (kind.is_dir(), kind.is_symlink()) {
+            (true, _) => "dir".to_string(),
+            (_, true) => "symlink".to_string(),
+            _ => "file".to_string()
+        }));
+        if entry.depth() > 0 {
+            triples.push(fact("in_dir", parent(&path)));
+        }
+        if kind.is_file() {
+            if let Some(extension) = entry.path().extension() {
+                triples.push(fact("extension", extension.to_string_lossy().to_string()));
+            }
+            match entry.metadata() {
+                Ok(metadata) => triples.push(fact("size", metadata.len().to_string())),
+                Err(e) => warn(e.to_string())
+            }
+        }
+    }
+    Ok(triples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use crate::rdf::triple;
+
+    #[test]
+    fn test_read_tree() {
+        let root = std::env::temp_dir().join(format!("predicat_fs_{}", std::process::id()));
+        fs::create_dir_all(root.join("src/bin")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join("src/lib.rs"), "pub mod x;").unwrap();
+        fs::write(root.join("src/bin/README"), "").unwrap();
+        fs::write(root.join("target/out.rs"), "").unwrap();
+        let options = WalkOptions { include: vec!["*.rs".to_string()], exclude: vec!["target".to_string()] };
+        let triples = read(&root, &options, &mut |_| ()).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(triples, vec![
+            triple(".", "is_a", "dir"),
+            triple("src", "is_a", "dir"), triple("src", "in_dir", "."),
+            triple("src/bin", "is_a", "dir"), triple("src/bin", "in_dir", "src"),
+            triple("src/lib.rs", "is_a", "file"), triple("src/lib.rs", "in_dir", "src"),
+            triple("src/lib.rs", "extension", "rs"), triple("src/lib.rs", "size", "10")]);
+        assert!(read(Path::new("/nonexistent/predicat"), &WalkOptions::default(), &mut |_| ()).is_err());
+        assert!(Patterns::new(&["a[".to_string()]).is_err());
+    }
+}
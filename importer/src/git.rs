@@ -0,0 +1,100 @@
+// The history of a local git repository, read from `git log`:
+//   <commit> is_a commit            <commit> parent <commit>
+//   <commit> author <name>          <commit> email <address>
+//   <commit> date <iso 8601>        <commit> message <first line>
+//   <commit> touches <file>
+// the commits being named by their full hash.
+
+use std::path::Path;
+use std::process::Command;
+use crate::rdf::Triple;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GitOptions {
+    // the revisions to read, like `main` or `v1.0..HEAD`, HEAD by default
+    pub revision: Option<String>,
+    pub max_count: Option<usize>
+}
+
+// a commit starts with a record separator, its fields being split by unit separators
+static FORMAT: &str = "--format=%x1e%H%x1f%P%x1f%an%x1f%ae%x1f%aI%x1f%s";
+
+fn commit_facts(record: &str) -> Vec<Triple> {
+    let mut lines = record.lines();
+    let fields = lines.next().unwrap_or_default().split('\u{1f}').collect::<Vec<_>>();
+    let (hash, parents, author, email, date, message) = match fields[..] {
+        [hash, parents, author, email, date, message] => (hash, parents, author, email, date, message),
+        _ => return vec![]
+    };
+    let fact = |link: &str, goal: &str| (hash.to_string(), link.to_string(), goal.to_string());
+    std::iter::once(fact("is_a", "commit"))
+        .chain(parents.split_whitespace().map(|parent| fact("parent", parent)))
+        .chain([("author", author), ("email", email), ("date", date), ("message", message)].into_iter()
+               .filter(|(_, goal)| !goal.is_empty())
+               .map(|(link, goal)| fact(link, goal)))
+        .chain(lines.filter(|file| !file.is_empty()).map(|file| fact("touches", file)))
+        .collect()
+}
+
+pub fn parse_log(log: &str) -> Vec<Triple> {
+    log.split('\u{1e}').flat_map(commit_facts).collect()
+}
+
+pub fn read(repository: &Path, options: &GitOptions) -> Result<Vec<Triple>, String> {
+    let mut command = Command::new("git");
+    command.arg("-C").arg(repository)
+        .args(["-c", "core.quotepath=off", "log", "--name-only", FORMAT]);
+    if let Some(max_count) = options.max_count {
+        command.arg(format!("--max-count={}", max_count));
+    }
+    // a revision starting with '-' isn't read as an option
+    command.arg("--end-of-options").args(options.revision.iter()).arg("--");
+    let output = command.output()
+        .map_err(|e| format!("git can't be run ({})", e))?;
+    match output.status.success() {
+        true => Ok(parse_log(&String::from_utf8_lossy(&output.stdout))),
+        false => Err(format!("git log failed on '{}' ({})", repository.display(),
+                             String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdf::triple;
+
+    #[test]
+    fn test_parse_log() {
+        let log = "\u{1e}b2\u{1f}a1\u{1f}Julie\u{1f}julie@x.org\u{1f}2024-01-02T10:00:00+01:00\u{1f}Fix the parser\n\n\
+                   parser/src/lib.rs\nREADME.md\n\
+                   \u{1e}a1\u{1f}\u{1f}Pierre\u{1f}\u{1f}2024-01-01T09:00:00+01:00\u{1f}Initial commit\n";
+        assert_eq!(parse_log(log), vec![
+            triple("b2", "is_a", "commit"), triple("b2", "parent", "a1"),
+            triple("b2", "author", "Julie"), triple("b2", "email", "julie@x.org"),
+            triple("b2", "date", "2024-01-02T10:00:00+01:00"), triple("b2", "message", "Fix the parser"),
+            triple("b2", "touches", "parser/src/lib.rs"), triple("b2", "touches", "README.md"),
+            triple("a1", "is_a", "commit"), triple("a1", "author", "Pierre"),
+            triple("a1", "date", "2024-01-01T09:00:00+01:00"), triple("a1", "message", "Initial commit")]);
+        assert!(read(Path::new("/nonexistent/predicat"), &GitOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_revision_is_not_an_option() {
+        let root = std::env::temp_dir().join(format!("predicat_git_{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("README.md"), "predicat\n").unwrap();
+        let git = |args: &[&str]| Command::new("git").arg("-C").arg(&root)
+            .args(["-c", "user.name=Julie", "-c", "user.email=julie@x.org"]).args(args)
+            .output().unwrap().status.success();
+        assert!(git(&["init", "-q"]) && git(&["add", "README.md"]) && git(&["commit", "-q", "-m", "Initial commit"]));
+        let facts = read(&root, &GitOptions::default()).unwrap();
+        let output = root.join("log.txt");
+        let options = GitOptions { revision: Some(format!("--output={}", output.display())), max_count: None };
+        let revision = read(&root, &options);
+        let written = output.exists();
+        std::fs::remove_dir_all(&root).unwrap();
+        assert!(facts.contains(&triple(&facts[0].0, "touches", "README.md")));
+        assert!(revision.is_err());
+        assert!(!written);
+    }
+}
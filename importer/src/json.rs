@@ -0,0 +1,167 @@
+// Every JSON object becomes the facts `<id> <key> <value>`, the id being its id field
+// or a blank node `_:<prefix>N`. A nested object is the value of its key, an array gives a
+// fact for each of its elements.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde_json::{Map, Value};
+use crate::rdf::Triple;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonOptions {
+    pub id_field: String,
+    // one document per line (JSON Lines)
+    pub lines: bool,
+    // the start of the blank nodes, different for each import so that the objects
+    // without id of two imports aren't merged
+    pub blank_prefix: String
+}
+
+static IMPORTS: AtomicUsize = AtomicUsize::new(0);
+
+// a prefix like `b18f2a3c9d01_0_`, from the time and the number of imports of the run
+pub fn unique_blank_prefix() -> String {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_micros()).unwrap_or_default();
+    format!("b{:x}_{}_", time, IMPORTS.fetch_add(1, Ordering::Relaxed))
+}
+
+impl Default for JsonOptions {
+    fn default() -> Self {
+        JsonOptions { id_field: "id".to_string(), lines: false, blank_prefix: unique_blank_prefix() }
+    }
+}
+
+struct Flattener<'a> {
+    id_field: &'a str,
+    blank_prefix: &'a str,
+    blanks: usize,
+    triples: Vec<Triple>
+}
+
+impl Flattener<'_> {
+    // the id of the object and whether it comes from its id field
+    fn name(&mut self, object: &Map<String, Value>) -> (String, bool) {
+        match object.get(self.id_field) {
+            Some(Value::String(id)) if !id.is_empty() => (id.clone(), true),
+            Some(Value::Number(id)) => (id.to_string(), true),
+            _ => {
+                self.blanks += 1;
+                (format!("_:{}{}", self.blank_prefix, self.blanks - 1), false)
+            }
+        }
+    }
+
+    fn object(&mut self, object: &Map<String, Value>) -> String {
+        let (id, named) = self.name(object);
+        self.fields(&id, object, named);
+        id
+    }
+
+    fn fields(&mut self, id: &str, object: &Map<String, Value>, named: bool) {
+        let id_field = self.id_field;
+        object.iter()
+            .filter(|(key, _)| !(named && key.as_str() == id_field))
+            .for_each(|(key, value)| self.value(id, key, value));
+    }
+
+    fn value(&mut self, subject: &str, key: &str, value: &Value) {
+        let goal = match value {
+            // a null or an empty string isn't a fact
+            Value::Null => return,
+            Value::String(s) if s.is_empty() => return,
+            Value::String(s) => s.clone(),
+            Value::Bool(b) => b.to_string(),
+            Value::Number(n) => n.to_string(),
+            Value::Array(values) => {
+                values.iter().for_each(|value| self.value(subject, key, value));
+                return
+            },
+            Value::Object(object) => {
+                let (id, named) = self.name(object);
+                self.triples.push((subject.to_string(), key.to_string(), id.clone()));
+                self.fields(&id, object, named);
+                return
+            }
+        };
+        self.triples.push((subject.to_string(), key.to_string(), goal));
+    }
+
+    fn document(&mut self, document: &Value) -> Result<(), String> {
+        match document {
+            Value::Object(object) => { self.object(object); Ok(()) },
+            Value::Array(documents) => documents.iter().try_for_each(|document| self.document(document)),
+            _ => Err(format!("the JSON value '{}' isn't an object", document))
+        }
+    }
+}
+
+pub fn read(input: &str, options: &JsonOptions) -> Result<Vec<Triple>, String> {
+    let mut flattener = Flattener {
+        id_field: &options.id_field,
+        blank_prefix: &options.blank_prefix,
+        blanks: 0,
+        triples: vec![]
+    };
+    match options.lines {
+        true => input.lines().enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .try_for_each(|(number, line)| serde_json::from_str(line)
+                          .map_err(|e| format!("the JSON of line {} is malformed ({})", number + 1, e))
+                          .and_then(|document| flattener.document(&document)))?,
+        false => serde_json::from_str(input)
+            .map_err(|e| format!("the JSON is malformed ({})", e))
+            .and_then(|document| flattener.document(&document))?
+    }
+    Ok(flattener.triples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rdf::triple;
+
+    fn options(id_field: &str, lines: bool) -> JsonOptions {
+        JsonOptions { id_field: id_field.to_string(), lines, blank_prefix: "b".to_string() }
+    }
+
+    #[test]
+    fn test_read_document() {
+        let input = r#"[{"id": "pierre", "age": 24, "friends": ["julie", "paul"],
+                         "address": {"city": "Paris", "zip": null}, "admin": false},
+                        {"name": "anonymous", "tags": [{"id": 7, "label": ""}]}]"#;
+        assert_eq!(
+            read(input, &options("id", false)),
+            Ok(vec![triple("pierre", "address", "_:b0"),
+                    triple("_:b0", "city", "Paris"),
+                    triple("pierre", "admin", "false"),
+                    triple("pierre", "age", "24"),
+                    triple("pierre", "friends", "julie"),
+                    triple("pierre", "friends", "paul"),
+                    triple("_:b1", "name", "anonymous"),
+                    triple("_:b1", "tags", "7")]));
+        assert!(read("[1, 2]", &JsonOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_read_lines() {
+        let options = options("login", true);
+        assert_eq!(
+            read("{\"login\": \"pierre\", \"id\": 3}\n\n{\"id\": 4}\n", &options),
+            Ok(vec![triple("pierre", "id", "3"), triple("_:b0", "id", "4")]));
+        assert_eq!(
+            read("{\"login\": \"pierre\"}\n{\"login\": \n", &options).map_err(|e| e.starts_with("the JSON of line 2")),
+            Err(true));
+    }
+
+    #[test]
+    fn test_blank_nodes_of_two_imports() {
+        let blanks = |triples: Vec<Triple>| triples.into_iter()
+            .filter(|(subject, _, _)| subject.starts_with("_:"))
+            .map(|(subject, _, _)| subject)
+            .collect::<Vec<_>>();
+        let first = blanks(read("{\"name\": \"pierre\"}", &JsonOptions::default()).unwrap());
+        let second = blanks(read("{\"name\": \"julie\"}", &JsonOptions::default()).unwrap());
+        assert_eq!((first.len(), second.len()), (1, 1));
+        assert_ne!(first, second);
+    }
+}
@@ -0,0 +1,467 @@
+// N-Triples and a practical subset of Turtle: prefixes, base, `;` and `,` lists, `a`,
+// literals with their language or datatype dropped, numbers and booleans.
+// Blank node property lists and collections aren't supported.
+//
+// The names of predicat (`pierre`, `ami`) live in the `urn:predicat:` namespace, the other
+// IRIs are kept whole and the literals keep their lexical form, like an `Element::String`.
+
+use std::collections::HashMap;
+
+pub type Triple = (String, String, String);
+
+pub fn triple(subject: &str, link: &str, goal: &str) -> Triple {
+    (subject.to_string(), link.to_string(), goal.to_string())
+}
+
+pub static NAMESPACE: &str = "urn:predicat:";
+static RDF_TYPE: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#type";
+
+static KNOWN_PREFIXES: &[(&str, &str)] = &[
+    ("rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#"),
+    ("rdfs", "http://www.w3.org/2000/01/rdf-schema#"),
+    ("xsd", "http://www.w3.org/2001/XMLSchema#"),
+    ("owl", "http://www.w3.org/2002/07/owl#"),
+    ("foaf", "http://xmlns.com/foaf/0.1/"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RdfFormat {
+    NTriples,
+    Turtle
+}
+
+impl TryFrom<&str> for RdfFormat {
+    type Error = String;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        match name {
+            "nt" | "ntriples" => Ok(RdfFormat::NTriples),
+            "ttl" | "turtle" => Ok(RdfFormat::Turtle),
+            other => Err(format!("The format '{}' isn't supported (expected nt or ttl)", other))
+        }
+    }
+}
+
+impl RdfFormat {
+    pub fn from_path(path: &str) -> Option<RdfFormat> {
+        path.rsplit_once('.').and_then(|(_, extension)| RdfFormat::try_from(extension).ok())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Iri(String),
+    Prefixed(String, String),
+    Blank(String),
+    Literal(String),
+    Word(String),
+    Punct(char)
+}
+
+// an N-Triples document is also a Turtle one
+pub fn read(format: RdfFormat, input: &str) -> Result<Vec<Triple>, String> {
+    match format {
+        RdfFormat::NTriples | RdfFormat::Turtle => read_turtle(input)
+    }
+}
+
+pub fn write(format: RdfFormat, triples: &[Triple]) -> String {
+    match format {
+        RdfFormat::NTriples => write_ntriples(triples),
+        RdfFormat::Turtle => write_turtle(triples)
+    }
+}
+
+fn unescape(s: &str) -> String {
+    let mut result = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match (c, c == '\\') {
+            (_, true) => match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some('r') => result.push('\r'),
+                Some(u @ ('u' | 'U')) => {
+                    let code = chars.by_ref().take(if u == 'u' { 4 } else { 8 }).collect::<String>();
+                    result.extend(u32::from_str_radix(&code, 16).ok().and_then(char::from_u32));
+                },
+                Some(other) => result.push(other),
+                None => ()
+            },
+            (c, false) => result.push(c)
+        }
+    }
+    result
+}
+
+fn lex(input: &str) -> Result<Vec<(Token, usize)>, String> {
+    let chars = input.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut line = 1;
+    let mut i = 0;
+    let error = |line: usize, message: &str| format!("line {}: {}", line, message);
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\n' => { line += 1; i += 1; },
+            c if c.is_whitespace() => i += 1,
+            '#' => while i < chars.len() && chars[i] != '\n' { i += 1 },
+            '<' => {
+                let end = chars[i..].iter().position(|x| *x == '>')
+                    .ok_or_else(|| error(line, "an IRI isn't closed"))?;
+                tokens.push((Token::Iri(unescape(&chars[i + 1..i + end].iter().collect::<String>())), line));
+                i += end + 1;
+            },
+            '"' | '\'' => {
+                let long = chars.get(i + 1) == Some(&c) && chars.get(i + 2) == Some(&c);
+                let delimiter = if long { 3 } else { 1 };
+                let start = i + delimiter;
+                let mut end = start;
+                loop {
+                    match chars.get(end) {
+                        None => return Err(error(line, "a literal isn't closed")),
+                        Some('\\') => end += 2,
+                        Some('\n') if !long => return Err(error(line, "a literal isn't closed")),
+                        Some(x) if *x == c && (!long || chars[end..].starts_with(&[c, c, c])) => break,
+                        Some(x) => { if *x == '\n' { line += 1 } end += 1 }
+                    }
+                }
+                tokens.push((Token::Literal(unescape(&chars[start..end].iter().collect::<String>())), line));
+                i = end + delimiter;
+                // the language and the datatype aren't kept
+                if chars.get(i) == Some(&'@') {
+                    i += 1;
+                    while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '-') { i += 1 }
+                } else if chars[i..].starts_with(&['^', '^']) {
+                    i += 2;
+                    let ends = |j: usize| chars.get(j).is_none_or(|x| x.is_whitespace() || ";,".contains(*x)
+                        || (*x == '.' && chars.get(j + 1).is_none_or(|y| y.is_whitespace())));
+                    match chars.get(i) {
+                        Some('<') => i += chars[i..].iter().position(|x| *x == '>').map(|x| x + 1).unwrap_or(0),
+                        _ => while !ends(i) { i += 1 }
+                    }
+                }
+            },
+            '.' | ';' | ',' => { tokens.push((Token::Punct(c), line)); i += 1; },
+            '[' | '(' => return Err(error(line, "blank node property lists and collections aren't supported")),
+            _ => {
+                let mut end = i;
+                while end < chars.len() && !chars[end].is_whitespace() && !"<>\"';,[]()".contains(chars[end]) { end += 1 }
+                // a name or a number doesn't end with a dot, it ends the statement
+                while end > i + 1 && chars[end - 1] == '.' { end -= 1 }
+                if end == i {
+                    return Err(error(line, &format!("unexpected character '{}'", c)));
+                }
+                let word = chars[i..end].iter().collect::<String>();
+                let token = match (word.strip_prefix("_:"), word.split_once(':')) {
+                    (Some(label), _) => Token::Blank(label.to_string()),
+                    (None, Some((prefix, local))) if !word.starts_with('@') =>
+                        Token::Prefixed(prefix.to_string(), unescape(local)),
+                    _ => Token::Word(word)
+                };
+                tokens.push((token, line));
+                i = end;
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+fn is_absolute_iri(value: &str) -> bool {
+    match value.split_once(':') {
+        Some((scheme, rest)) => scheme.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+            && !rest.is_empty()
+            && !value.chars().any(|c| c.is_whitespace() || "<>\"{}|^`\\".contains(c)),
+        None => false
+    }
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], s.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(byte)) => { decoded.push(byte); i += 3; },
+            (byte, _) => { decoded.push(byte); i += 1; }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn percent_encode(s: &str) -> String {
+    s.bytes()
+        .map(|byte| match byte.is_ascii_alphanumeric() || b"_-.~".contains(&byte) {
+            true => (byte as char).to_string(),
+            false => format!("%{:02X}", byte)
+        })
+        .collect()
+}
+
+// the value a predicat fact keeps of an IRI
+fn iri_value(iri: &str) -> String {
+    match iri.strip_prefix(NAMESPACE) {
+        Some(name) => percent_decode(name),
+        None => iri.to_string()
+    }
+}
+
+struct Reader {
+    prefixes: HashMap<String, String>,
+    base: String
+}
+
+impl Reader {
+    fn resolve(&self, iri: &str) -> String {
+        match is_absolute_iri(iri) {
+            true => iri.to_string(),
+            false => format!("{}{}", self.base, iri)
+        }
+    }
+
+    fn value(&self, token: &Token, line: usize) -> Result<String, String> {
+        match token {
+            Token::Iri(iri) => Ok(iri_value(&self.resolve(iri))),
+            Token::Prefixed(prefix, local) => self.prefixes.get(prefix)
+                .map(|namespace| iri_value(&format!("{}{}", namespace, local)))
+                .ok_or_else(|| format!("line {}: the prefix '{}' isn't declared", line, prefix)),
+            Token::Blank(label) => Ok(format!("_:{}", label)),
+            Token::Literal(literal) => Ok(literal.clone()),
+            Token::Word(word) if word == "a" => Ok(iri_value(RDF_TYPE)),
+            Token::Word(word) if word == "true" || word == "false" || word.parse::<f64>().is_ok() => Ok(word.clone()),
+            other => Err(format!("line {}: unexpected {:?}", line, other))
+        }
+    }
+}
+
+pub fn read_turtle(input: &str) -> Result<Vec<Triple>, String> {
+    let tokens = lex(input)?;
+    let mut reader = Reader { prefixes: HashMap::new(), base: NAMESPACE.to_string() };
+    let mut triples = vec![];
+    let mut i = 0;
+    let last_line = tokens.last().map(|(_, line)| *line).unwrap_or(1);
+    let token = |i: usize| tokens.get(i).cloned().ok_or_else(|| format!("line {}: the document ends too early", last_line));
+    while i < tokens.len() {
+        let (first, line) = token(i)?;
+        match first {
+            Token::Word(word) if ["@prefix", "PREFIX", "prefix"].contains(&word.as_str()) => {
+                match (token(i + 1)?, token(i + 2)?) {
+                    ((Token::Prefixed(prefix, local), _), (Token::Iri(iri), _)) if local.is_empty() => {
+                        reader.prefixes.insert(prefix, reader.resolve(&iri));
+                    },
+                    _ => return Err(format!("line {}: a prefix is malformed", line))
+                }
+                i += 3;
+                if word == "@prefix" {
+                    match token(i)? { (Token::Punct('.'), _) => i += 1, _ => return Err(format!("line {}: a prefix isn't ended by '.'", line)) }
+                }
+            },
+            Token::Word(word) if ["@base", "BASE", "base"].contains(&word.as_str()) => {
+                match token(i + 1)? {
+                    (Token::Iri(iri), _) => reader.base = reader.resolve(&iri),
+                    _ => return Err(format!("line {}: a base is malformed", line))
+                }
+                i += 2;
+                if word == "@base" {
+                    match token(i)? { (Token::Punct('.'), _) => i += 1, _ => return Err(format!("line {}: a base isn't ended by '.'", line)) }
+                }
+            },
+            Token::Literal(_) => return Err(format!("line {}: a literal can't be a subject", line)),
+            subject => {
+                let subject = reader.value(&subject, line)?;
+                i += 1;
+                loop {
+                    let (link, line) = token(i)?;
+                    let link = reader.value(&link, line)?;
+                    i += 1;
+                    loop {
+                        let (goal, line) = token(i)?;
+                        triples.push((subject.clone(), link.clone(), reader.value(&goal, line)?));
+                        i += 1;
+                        match token(i)? {
+                            (Token::Punct(','), _) => i += 1,
+                            _ => break
+                        }
+                    }
+                    match token(i)? {
+                        (Token::Punct(';'), _) => {
+                            i += 1;
+                            // a trailing ';' before the end of the statement
+                            if let (Token::Punct('.'), _) = token(i)? { break }
+                        },
+                        (Token::Punct('.'), _) => break,
+                        (other, line) => return Err(format!("line {}: expected ';', ',' or '.' but found {:?}", line, other))
+                    }
+                }
+                i += 1;
+            }
+        }
+    }
+    Ok(triples)
+}
+
+enum Node {
+    Iri(String),
+    Blank(String),
+    Literal(String)
+}
+
+fn is_name(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// the subjects and links are always resources, a goal is a literal unless it's a name
+fn node(value: &str, position: usize) -> Node {
+    match value.strip_prefix("_:") {
+        Some(label) if is_name(label) => Node::Blank(label.to_string()),
+        _ if is_absolute_iri(value) => Node::Iri(value.to_string()),
+        _ if position < 2 || (is_name(value) && value.parse::<f64>().is_err()) =>
+            Node::Iri(format!("{}{}", NAMESPACE, percent_encode(value))),
+        _ => Node::Literal(value.to_string())
+    }
+}
+
+fn escape_literal(value: &str) -> String {
+    value.chars()
+        .map(|c| match c {
+            '"' => "\\\"".to_string(),
+            '\\' => "\\\\".to_string(),
+            '\n' => "\\n".to_string(),
+            '\r' => "\\r".to_string(),
+            '\t' => "\\t".to_string(),
+            c => c.to_string()
+        })
+        .collect()
+}
+
+fn ntriples_node(node: &Node) -> String {
+    match node {
+        Node::Iri(iri) => format!("<{}>", iri),
+        Node::Blank(label) => format!("_:{}", label),
+        Node::Literal(literal) => format!("\"{}\"", escape_literal(literal))
+    }
+}
+
+pub fn write_ntriples(triples: &[Triple]) -> String {
+    triples.iter()
+        .map(|(s, l, g)| format!("{} {} {} .\n", ntriples_node(&node(s, 0)), ntriples_node(&node(l, 1)), ntriples_node(&node(g, 2))))
+        .collect()
+}
+
+// the IRIs are written with a prefix when their local part is a plain name
+struct Writer {
+    prefixes: Vec<(String, String)>
+}
+
+impl Writer {
+    fn prefixed(&mut self, iri: &str) -> Option<String> {
+        let split = iri.rfind(['#', '/', ':']).map(|x| x + 1)?;
+        let (namespace, local) = iri.split_at(split);
+        if !is_name(local) || local.chars().next().is_some_and(|c| c.is_ascii_digit()) && namespace != NAMESPACE {
+            return None;
+        }
+        let prefix = match self.prefixes.iter().find(|(_, ns)| ns == namespace) {
+            Some((prefix, _)) => prefix.clone(),
+            None => {
+                let prefix = match (namespace == NAMESPACE, KNOWN_PREFIXES.iter().find(|(_, ns)| *ns == namespace)) {
+                    (true, _) => String::new(),
+                    (false, Some((prefix, _))) => prefix.to_string(),
+                    (false, None) => format!("ns{}", self.prefixes.len())
+                };
+                self.prefixes.push((prefix.clone(), namespace.to_string()));
+                prefix
+            }
+        };
+        Some(format!("{}:{}", prefix, local))
+    }
+
+    fn node(&mut self, node: &Node) -> String {
+        match node {
+            Node::Iri(iri) if iri == RDF_TYPE => "a".to_string(),
+            Node::Iri(iri) => self.prefixed(iri).unwrap_or_else(|| format!("<{}>", iri)),
+            other => ntriples_node(other)
+        }
+    }
+}
+
+pub fn write_turtle(triples: &[Triple]) -> String {
+    let mut writer = Writer { prefixes: vec![(String::new(), NAMESPACE.to_string())] };
+    let mut subjects: Vec<(String, Vec<String>)> = vec![];
+    for (s, l, g) in triples {
+        let subject = writer.node(&node(s, 0));
+        let predicate_object = format!("{} {}", writer.node(&node(l, 1)), writer.node(&node(g, 2)));
+        match subjects.iter_mut().find(|(x, _)| *x == subject) {
+            Some((_, objects)) => objects.push(predicate_object),
+            None => subjects.push((subject, vec![predicate_object]))
+        }
+    }
+    let header = writer.prefixes.iter()
+        .map(|(prefix, namespace)| format!("@prefix {}: <{}> .\n", prefix, namespace))
+        .collect::<String>();
+    let body = subjects.iter()
+        .map(|(subject, objects)| format!("{} {} .\n", subject, objects.join(" ;\n    ")))
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("{}\n{}", header, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_ntriples() {
+        let input = "<urn:predicat:pierre> <urn:predicat:ami> <urn:predicat:julie> .\n\
+                     # a comment\n\
+                     <http://example.org/anne> <http://xmlns.com/foaf/0.1/name> \"Anne \\\"Nan\\\" Dupont\"@fr .\n\
+                     _:b0 <urn:predicat:age> \"24\"^^<http://www.w3.org/2001/XMLSchema#integer> .\n";
+        assert_eq!(
+            read(RdfFormat::NTriples, input),
+            Ok(vec![triple("pierre", "ami", "julie"),
+                    triple("http://example.org/anne", "http://xmlns.com/foaf/0.1/name", "Anne \"Nan\" Dupont"),
+                    triple("_:b0", "age", "24")]));
+    }
+
+    #[test]
+    fn test_read_turtle() {
+        let input = "@prefix : <urn:predicat:> .\n\
+                     PREFIX foaf: <http://xmlns.com/foaf/0.1/>\n\
+                     :pierre :ami :julie, :anne ;\n    a foaf:Person ;\n    :age 24 .\n\
+                     :julie foaf:name 'Julie' .\n";
+        assert_eq!(
+            read_turtle(input),
+            Ok(vec![triple("pierre", "ami", "julie"),
+                    triple("pierre", "ami", "anne"),
+                    triple("pierre", RDF_TYPE, "http://xmlns.com/foaf/0.1/Person"),
+                    triple("pierre", "age", "24"),
+                    triple("julie", "http://xmlns.com/foaf/0.1/name", "Julie")]));
+        assert!(read_turtle(":pierre unknown:ami :julie .").is_err());
+        assert!(read_turtle(":pierre :ami [ :age 3 ] .").is_err());
+        for stray in [']', ')', '>'] {
+            assert_eq!(read_turtle(&format!(":a :b :c {} .", stray)),
+                       Err(format!("line 1: unexpected character '{}'", stray)));
+        }
+    }
+
+    #[test]
+    fn test_write_and_read_back() {
+        let triples = vec![triple("pierre", "ami", "julie"),
+                           triple("pierre", "age", "24"),
+                           triple("jean claude", "est", "l'homme"),
+                           triple("pierre", "http://xmlns.com/foaf/0.1/name", "Pierre Dupont")];
+        assert_eq!(
+            write_ntriples(&triples[..2]),
+            "<urn:predicat:pierre> <urn:predicat:ami> <urn:predicat:julie> .\n<urn:predicat:pierre> <urn:predicat:age> \"24\" .\n");
+        assert_eq!(
+            write_turtle(&triples),
+            "@prefix : <urn:predicat:> .\n@prefix foaf: <http://xmlns.com/foaf/0.1/> .\n\n\
+             :pierre :ami :julie ;\n    :age \"24\" ;\n    foaf:name \"Pierre Dupont\" .\n\n\
+             <urn:predicat:jean%20claude> :est \"l'homme\" .\n");
+        for format in [RdfFormat::NTriples, RdfFormat::Turtle] {
+            let read_back = read(format, &write(format, &triples)).unwrap();
+            assert_eq!(read_back.len(), triples.len());
+            assert!(triples.iter().all(|x| read_back.contains(x)));
+        }
+    }
+}
@@ -1,23 +1,99 @@
 pub use super::sqlite_knowledge::SqliteKnowledge;
-use parser::base_parser::{PredicatAST, Triplet};
+pub use super::memory_knowledge::MemoryKnowledge;
+pub use super::plan::{Statistics, LinkStatistics};
+use parser::base_parser::{PredicatAST, Triplet, CommandType};
 use base_context::simple_context::DataFrame;
+use base_context::stream::RowStream;
+use crate::inference::{bind_triplet, instantiate};
+use std::collections::HashSet;
+use std::fmt::Debug;
 
+pub type Fact = (String, String, String);
 
-pub fn new_knowledge<K: Knowledgeable<DataFrame>>(kind: &str) -> Result<K, String> {
-    match kind {
-        "sqlite" => Ok(K::new()),
-        k => Err(format!("There is no '{}' knowledge availiable", k))
-    }
+// the facts kept at once by a bulk load running the rules, whatever the size of the input
+pub(crate) static INFERENCE_CHUNK: usize = 10_000;
+
+type Backend = fn() -> Box<dyn Knowledgeable<DataFrame>>;
+
+// every store the interpreter can work on, the first one is the default
+static BACKENDS: &[(&str, Backend)] = &[
+    ("sqlite", || Box::new(SqliteKnowledge::new())),
+    ("memory", || Box::new(MemoryKnowledge::new())),
+];
+
+pub fn backend_names() -> Vec<&'static str> {
+    BACKENDS.iter().map(|(name, _)| *name).collect()
+}
+
+pub fn new_knowledge(kind: &str) -> Result<Box<dyn Knowledgeable<DataFrame>>, String> {
+    BACKENDS.iter()
+        .find(|(name, _)| *name == kind)
+        .map(|(_, backend)| backend())
+        .ok_or_else(|| format!("There is no '{}' knowledge available (expected one of: {})",
+                               kind, backend_names().join(", ")))
 }
 
 pub trait Knowledgeable<T: Joinable + Clone>: Command<T> + FactManager + RuleManager<T> + Cache {
-    fn new() -> Self;
+    fn new() -> Self where Self: Sized;
 
     fn clear_all(&self) {
         self.clear_facts();
         self.clear_rules();
         self.clear_cache();
     }
+
+    // inserts the facts at once then, if asked, runs the rules on them by chunks of
+    // INFERENCE_CHUNK facts, gives back the number of facts read and of facts inferred
+    fn bulk_load(&self, facts: &mut dyn Iterator<Item = Fact>, progress: &mut dyn FnMut(usize), infer: bool) -> Result<(usize, usize), String> {
+        if !(infer && self.get_rules().iter().any(|rule| rule.enabled)) {
+            return Ok((self.bulk_insert(facts, progress)?, 0));
+        }
+        let (mut count, mut inferred) = (0, 0);
+        loop {
+            let chunk = (&mut *facts).take(INFERENCE_CHUNK).collect::<Vec<_>>();
+            if chunk.is_empty() {
+                return Ok((count, inferred));
+            }
+            let read = self.bulk_insert(&mut chunk.iter().cloned(), &mut |read| progress(count + read))?;
+            count += read;
+            inferred += self.infer_to_fixpoint(chunk)?;
+        }
+    }
+
+    // the consequences of the added facts, then the consequences of those until nothing new comes out
+    fn infer_to_fixpoint(&self, facts: Vec<Fact>) -> Result<usize, String> {
+        let rules = self.get_rules().into_iter()
+            .filter(|rule| rule.enabled)
+            .map(|rule| (rule.modifier.clone(), rule.trigger(), rule.conclusion))
+            .collect::<Vec<_>>();
+        let mut seen: HashSet<(&str, Fact)> = facts.iter().map(|fact| ("add", fact.clone())).collect();
+        let mut pending: Vec<(&str, Fact)> = facts.into_iter().map(|fact| ("add", fact)).collect();
+        let mut inferred = 0;
+        while !pending.is_empty() {
+            let derived = pending.iter()
+                .flat_map(|(modifier, fact)| rules.iter()
+                    .filter(move |(trigger_modifier, _, _)| trigger_modifier == modifier)
+                    .filter_map(move |(_, trigger, conclusion)| bind_triplet(fact, trigger)
+                                .and_then(|bindings| instantiate(conclusion, &bindings))))
+                .flat_map(|ast| match ast {
+                    PredicatAST::AddModifier(triplets) => triplets.iter().map(|tri| ("add", tri.to_tuple())).collect(),
+                    PredicatAST::DeleteModifier(triplets) => triplets.iter().map(|tri| ("delete", tri.to_tuple())).collect(),
+                    _ => vec![]
+                })
+                .filter(|(modifier, fact)| seen.insert((modifier, fact.clone())))
+                .collect::<Vec<_>>();
+            let (added, deleted): (Vec<_>, Vec<_>) = derived.iter().partition(|(modifier, _)| *modifier == "add");
+            self.bulk_insert(&mut added.into_iter().map(|(_, fact)| fact.clone()), &mut |_| ())?;
+            if !deleted.is_empty() {
+                self.execute_command(&PredicatAST::DeleteModifier(deleted.into_iter()
+                    .map(|(_, (s, l, g))| Triplet::Teee(s.clone(), l.clone(), g.clone()))
+                    .collect()))?;
+            }
+            inferred += derived.len();
+            pending = derived;
+        }
+        Ok(inferred)
+    }
 }
 
 pub trait Cache {
@@ -27,42 +103,129 @@ pub trait Cache {
 }
 
 pub trait Joinable {
-    fn join(a: Self, b: Self) -> Self;
+    fn join(a: Self, b: Self) -> Result<Self, String> where Self: Sized;
 }
 
-pub trait Command<Data: Joinable + Clone>: Cache {
-    type Language;
+// the backends working with an intermediate language (like sql)
+pub trait Translator<Data: Joinable + Clone>: Cache {
+    type Language: Debug;
     fn get(&self, cmds: &str) -> Data;
-    fn get_all(&self) -> Data; // get a table of the datas included
     fn modify(&self, cmds: &str) -> Result<Data, &str>;
     fn translate<'a>(&'a self, s: &PredicatAST) -> Result<Vec<Self::Language>, &str>;
-    fn execute(&self, s: &Self::Language) -> Data;
-    fn is_invalid(&self, cmd: &PredicatAST) -> bool;
-    fn infer_commands_from(&self, cmds: &PredicatAST) -> Vec<String>;
+    fn execute(&self, s: &Self::Language) -> Result<Data, String>;
+
+    fn translate_and_execute(&self, subcmd: &PredicatAST) -> Result<Data, String> {
+        let cmd = self.store_to_cache(subcmd);
+        self.translate(&cmd).map_err(str::to_string)?.iter()
+            .map(|cmd| self.execute(cmd))
+            .reduce(|a, b| Data::join(a?, b?))
+            .unwrap_or_else(|| Err(format!("The command '{}' has nothing to run", String::from(cmd.clone()))))
+    }
+
+    fn explain_translation(&self, cmd: &PredicatAST) -> Result<Vec<String>, String> {
+        self.translate(cmd)
+            .map(|translation| translation.iter().map(|x| format!("{:?}", x)).collect())
+            .map_err(str::to_string)
+    }
+}
+
+pub trait Command<Data: Joinable + Clone>: Cache {
+    fn get_all(&self) -> Data; // get a table of the datas included
+    fn execute_command(&self, subcmd: &PredicatAST) -> Result<Data, String>;
+    // the rows of a query as they are read, any other command being executed first
+    fn stream(&self, query: &PredicatAST) -> Result<RowStream<'_>, String>;
+    fn explain(&self, cmd: &PredicatAST) -> Result<Vec<String>, String>; // how the backend would run the command
+    fn validate(&self, cmd: &PredicatAST) -> Result<Vec<String>, String>;
     fn infer_command_from_triplet(&self, modifier: &str, tri: &Triplet) -> Vec<String>;
 
-    fn valid_commands(&self, cmds: Vec<PredicatAST>) -> Option<Vec<PredicatAST>> {
-            cmds.iter().all(|x| !self.is_invalid(x)).then_some(cmds)
+    fn infer_commands_from(&self, cmd: &PredicatAST) -> Vec<String> {
+        match cmd {
+            PredicatAST::AddModifier(v_of_tri) => v_of_tri.iter()
+                .flat_map(|x| self.infer_command_from_triplet("add", x))
+                .collect::<Vec<_>>(),
+            PredicatAST::DeleteModifier(v_of_tri) => v_of_tri.iter()
+                .flat_map(|x| self.infer_command_from_triplet("delete", x))
+                .collect::<Vec<_>>(),
+            _ => vec![]
+        }
     }
 
-    fn execute_command(&self, subcmd: &PredicatAST) -> Data {
-        Some(subcmd)
-            .map(|cmd| self.store_to_cache(&cmd))
-            .map(|cmd| self.translate(&cmd).expect("The translation gone wrong"))
-            .unwrap().iter()
-            .map(|cmd| self.execute(cmd))
-            .reduce(Data::join)
-            .expect("The contexts don't have the right contents")
+    // returns the commands with the warnings they raised or the first rejection
+    fn valid_commands(&self, cmds: Vec<PredicatAST>) -> Result<(Vec<PredicatAST>, Vec<String>), String> {
+        let warnings = cmds.iter()
+            .map(|x| self.validate(x))
+            .collect::<Result<Vec<_>, String>>()?
+            .concat();
+        Ok((cmds, warnings))
     }
 }
 
 pub trait FactManager {
     fn clear_facts(&self);
     fn save_facts(&self, modifier: &str, subject: &str, link: &str, goal: &str);
+    fn get_links(&self) -> Vec<String>;
+    fn count_facts(&self) -> usize;
+    fn statistics(&self) -> Statistics;
+    // stores the facts without going through the commands, the progress gets the number of facts read so far
+    fn bulk_insert(&self, facts: &mut dyn Iterator<Item = Fact>, progress: &mut dyn FnMut(usize)) -> Result<usize, String>;
+}
+
+// one trigger of an infer command, as it goes to the storage
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleRecord {
+    pub name: Option<String>,
+    pub modifier: CommandType,
+    pub premise: Triplet,
+    pub conclusion: PredicatAST
+}
+
+impl RuleRecord {
+    pub fn from_infer(ast: &PredicatAST) -> Vec<RuleRecord> {
+        match ast {
+            PredicatAST::Infer(name, (modifier, triggers), conclusion) => triggers.iter()
+                .map(|premise| RuleRecord {
+                    name: name.clone(),
+                    modifier: *modifier,
+                    premise: premise.clone(),
+                    conclusion: *conclusion.clone()
+                }).collect(),
+            _ => vec![]
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub id: i64,
+    pub name: Option<String>,
+    pub modifier: String,
+    pub premise: Triplet,
+    pub conclusion: PredicatAST,
+    pub enabled: bool
+}
+
+impl Rule {
+    pub fn trigger(&self) -> (String, String, String) {
+        self.premise.to_tuple_with_variable()
+    }
+}
+
+pub fn rules_to_dataframe(rules: &[Rule]) -> DataFrame {
+    rules.iter()
+        .flat_map(|rule| [
+                  ("id".to_string(), rule.id.to_string()),
+                  ("name".to_string(), rule.name.clone().unwrap_or_default()),
+                  ("trigger".to_string(), format!("{} {}", rule.modifier, String::from(rule.premise.clone()))),
+                  ("command".to_string(), String::from(rule.conclusion.clone())),
+                  ("enabled".to_string(), rule.enabled.to_string())])
+        .collect::<Vec<_>>()
+        .try_into().unwrap_or_default()
 }
 
 pub trait RuleManager<Data> {
-    fn store_rule(&self, s: &str) -> Data; 
-    fn get_rules(&self) -> Vec<String>;
+    fn store_rule(&self, rule: &RuleRecord) -> Result<Data, String>;
+    fn get_rules(&self) -> Vec<Rule>;
+    fn drop_rule(&self, key: &str) -> Result<Data, String>;
+    fn set_rule_state(&self, key: &str, enabled: bool) -> Result<Data, String>;
     fn clear_rules(&self);
 }
@@ -0,0 +1,62 @@
+use parser::base_parser::{PredicatAST, Triplet};
+
+pub fn is_variable(s: &str) -> bool {
+    s.starts_with('$')
+}
+
+// the values taken by the variables of the pattern if the fact matches it
+pub fn bind_triplet((sub1, lin1, goa1): &(String, String, String), (sub2, lin2, goa2): &(String, String, String)) -> Option<Vec<(String, String)>> {
+    let mut bindings: Vec<(String, String)> = vec![];
+    for (value, pattern) in [(sub1, sub2), (lin1, lin2), (goa1, goa2)] {
+        match (is_variable(pattern), bindings.iter().find(|(var, _)| var == pattern)) {
+            (true, Some((_, bound))) if bound != value => return None,
+            (true, None) => bindings.push((pattern.to_string(), value.to_string())),
+            (false, _) if pattern != value => return None,
+            _ => ()
+        }
+    }
+    Some(bindings)
+}
+
+pub fn instantiate(conclusion: &PredicatAST, bindings: &[(String, String)]) -> Option<PredicatAST> {
+    let replace = |x: String| bindings.iter()
+        .find(|(var, _)| *var == x)
+        .map(|(_, value)| value.clone())
+        .unwrap_or(x);
+    let substitute = |triplets: &[Triplet]| triplets.iter()
+        .map(|tri| tri.to_tuple_with_variable())
+        .map(|(s, l, g)| (replace(s), replace(l), replace(g)))
+        .map(|(s, l, g)| Triplet::from_tuple_with_variable((&s, &l, &g)))
+        .collect::<Vec<_>>();
+    match conclusion {
+        PredicatAST::AddModifier(triplets) => Some(PredicatAST::AddModifier(substitute(triplets))),
+        PredicatAST::DeleteModifier(triplets) => Some(PredicatAST::DeleteModifier(substitute(triplets))),
+        _ => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tuple(s: &str, l: &str, g: &str) -> (String, String, String) {
+        (s.to_string(), l.to_string(), g.to_string())
+    }
+
+    #[test]
+    fn test_bind_triplet() {
+        assert_eq!(
+            bind_triplet(&tuple("pierre", "ami", "emy"), &tuple("$A", "ami", "$B")),
+            Some(vec![("$A".to_string(), "pierre".to_string()), ("$B".to_string(), "emy".to_string())]));
+        assert_eq!(bind_triplet(&tuple("pierre", "ami", "emy"), &tuple("$A", "ami", "$A")), None);
+        assert_eq!(bind_triplet(&tuple("pierre", "ami", "emy"), &tuple("$A", "voisin", "$B")), None);
+    }
+
+    #[test]
+    fn test_instantiate() {
+        let conclusion = PredicatAST::AddModifier(vec![Triplet::Tvev("B".to_string(), "ami".to_string(), "A".to_string())]);
+        assert_eq!(
+            instantiate(&conclusion, &[("$A".to_string(), "pierre".to_string()), ("$B".to_string(), "emy".to_string())]),
+            Some(PredicatAST::AddModifier(vec![Triplet::Teee("emy".to_string(), "ami".to_string(), "pierre".to_string())])));
+    }
+}
@@ -1,11 +1,24 @@
 mod sqlite_knowledge;
+mod rule_validation;
+mod inference;
+mod plan;
+mod memory_knowledge;
 pub mod base_knowledge;
 
 pub use base_knowledge::{
     SqliteKnowledge,
+    MemoryKnowledge,
     Knowledgeable,
     new_knowledge,
+    backend_names,
     Cache,
-    RuleManager
+    FactManager,
+    RuleManager,
+    Rule,
+    RuleRecord,
+    Fact,
+    Statistics,
+    LinkStatistics,
+    rules_to_dataframe
 };
 
@@ -0,0 +1,586 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use base_context::simple_context::DataFrame;
+use base_context::stream::RowStream;
+use parser::base_parser::{PredicatAST, Triplet, Comp};
+use base_context::context_traits::Var;
+use crate::base_knowledge::{Command, FactManager, Cache, RuleManager, Knowledgeable, Rule, RuleRecord, Fact, rules_to_dataframe};
+use crate::rule_validation::validate_rule;
+use crate::inference::{bind_triplet, instantiate};
+use crate::plan::{Plan, Term, Statistics, LinkStatistics, plan_query, plan_to_dataframe, pattern_variables};
+
+type Index = BTreeMap<String, BTreeMap<String, BTreeSet<String>>>;
+type Row = BTreeMap<String, String>;
+
+// a table of bindings, the columns keep their order of appearance
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Table {
+    columns: Vec<String>,
+    rows: Vec<Row>
+}
+
+// every fact is stored three times so that any pattern starts from a bound position,
+// the insertion order is kept to give the results back like the sqlite store does
+#[derive(Debug, Default)]
+struct Facts {
+    spo: Index,
+    pos: Index,
+    osp: Index,
+    order: HashMap<Fact, usize>,
+    inserted: usize
+}
+
+fn index_insert(index: &mut Index, a: &str, b: &str, c: &str) -> bool {
+    index.entry(a.to_string()).or_default()
+        .entry(b.to_string()).or_default()
+        .insert(c.to_string())
+}
+
+fn index_remove(index: &mut Index, a: &str, b: &str, c: &str) {
+    if let Some(second) = index.get_mut(a) {
+        if let Some(third) = second.get_mut(b) {
+            third.remove(c);
+            if third.is_empty() { second.remove(b); }
+        }
+        if second.is_empty() { index.remove(a); }
+    }
+}
+
+// the (a, b, c) entries of an index where the bound positions are equal
+fn index_scan(index: &Index, a: Option<&str>, b: Option<&str>, c: Option<&str>) -> Vec<Fact> {
+    let firsts = match a {
+        Some(a) => index.get_key_value(a).into_iter().collect::<Vec<_>>(),
+        None => index.iter().collect()
+    };
+    firsts.into_iter()
+        .flat_map(|(a, second)| {
+            let seconds = match b {
+                Some(b) => second.get_key_value(b).into_iter().collect::<Vec<_>>(),
+                None => second.iter().collect()
+            };
+            seconds.into_iter().flat_map(move |(b, third)| third.iter()
+                .filter(move |x| c.is_none_or(|c| c == x.as_str()))
+                .map(move |c| (a.clone(), b.clone(), c.clone())))
+        })
+        .collect()
+}
+
+impl Facts {
+    fn insert(&mut self, (s, l, g): &Fact) {
+        if index_insert(&mut self.spo, s, l, g) {
+            index_insert(&mut self.pos, l, g, s);
+            index_insert(&mut self.osp, g, s, l);
+            self.inserted += 1;
+            self.order.insert((s.clone(), l.clone(), g.clone()), self.inserted);
+        }
+    }
+
+    fn remove(&mut self, (s, l, g): &Fact) {
+        index_remove(&mut self.spo, s, l, g);
+        index_remove(&mut self.pos, l, g, s);
+        index_remove(&mut self.osp, g, s, l);
+        self.order.remove(&(s.clone(), l.clone(), g.clone()));
+    }
+
+    fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    // the facts matching the bound positions, read from the index starting with one of them
+    fn scan(&self, s: Option<&str>, l: Option<&str>, g: Option<&str>) -> Vec<Fact> {
+        let mut facts: Vec<Fact> = match (s, l, g) {
+            (Some(_), _, _) | (None, None, None) => index_scan(&self.spo, s, l, g),
+            (None, Some(_), _) => index_scan(&self.pos, l, g, s).into_iter()
+                .map(|(l, g, s)| (s, l, g)).collect(),
+            (None, None, Some(_)) => index_scan(&self.osp, g, s, l).into_iter()
+                .map(|(g, s, l)| (s, l, g)).collect()
+        };
+        facts.sort_by_key(|fact| self.order.get(fact));
+        facts
+    }
+
+    // the distinct values at one position of the facts
+    fn domain(&self, position: usize) -> Vec<String> {
+        match position {
+            0 => self.spo.keys().cloned().collect(),
+            1 => self.pos.keys().cloned().collect(),
+            _ => self.osp.keys().cloned().collect()
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryKnowledge {
+    facts: RefCell<Facts>,
+    rules: RefCell<Vec<Rule>>,
+    next_rule_id: RefCell<i64>,
+    cache: RefCell<HashSet<String>>
+}
+
+fn to_fact(tri: &Triplet) -> Fact {
+    tri.to_tuple_with_variable()
+}
+
+fn join_columns(a: &[String], b: &[String]) -> Vec<String> {
+    a.iter().chain(b.iter().filter(|x| !a.contains(x))).cloned().collect()
+}
+
+// a hash join on the shared columns of two tables
+fn join(a: Table, b: Table) -> Table {
+    let shared = b.columns.iter().filter(|x| a.columns.contains(x)).cloned().collect::<Vec<_>>();
+    let key = |row: &Row| shared.iter().map(|col| row.get(col).cloned()).collect::<Vec<_>>();
+    let mut by_key: HashMap<Vec<Option<String>>, Vec<&Row>> = HashMap::new();
+    b.rows.iter().for_each(|y| by_key.entry(key(y)).or_default().push(y));
+    let rows = a.rows.iter()
+        .flat_map(|x| by_key.get(&key(x)).into_iter().flatten()
+                  .map(move |y| x.clone().into_iter().chain((*y).clone()).collect::<Row>()))
+        .collect();
+    Table { columns: join_columns(&a.columns, &b.columns), rows }
+}
+
+// a plan whose rows can be read from the indexes for each row binding the given columns
+fn probeable(plan: &Plan, bound: &[String]) -> bool {
+    match plan {
+        Plan::Scan(pattern) => !pattern_variables(pattern).is_empty(),
+        Plan::AntiScan(pattern) => pattern_variables(pattern).iter().all(|(_, var)| bound.contains(var)),
+        Plan::Filter(plan, _) => probeable(plan, bound),
+        _ => false
+    }
+}
+
+fn table_to_dataframe(table: &Table, columns: &[String]) -> DataFrame {
+    let rows = table.rows.iter()
+        .map(|row| columns.iter().map(|col| row.get(col).cloned().unwrap_or_default()).collect())
+        .collect();
+    DataFrame::from_rows(&columns.iter().map(|x| &x[..]).collect::<Vec<_>>(), rows)
+        .unwrap_or_default()
+}
+
+fn term_pattern(term: &Term) -> String {
+    match term {
+        Term::Var(var) => format!("${}", var),
+        Term::Const(c) => c.clone()
+    }
+}
+
+impl MemoryKnowledge {
+    // the bindings of the variables of a pattern, a pattern without variables gives its fact back
+    fn scan(&self, pattern: &[Term; 3]) -> Table {
+        let variables = pattern_variables(pattern);
+        let [s, l, g] = pattern.clone().map(|term| term_pattern(&term));
+        let facts = self.facts.borrow();
+        let found = facts.scan(pattern[0].constant(), pattern[1].constant(), pattern[2].constant());
+        match variables.is_empty() {
+            true => Table {
+                columns: vec!["subject".to_string(), "link".to_string(), "goal".to_string()],
+                rows: found.into_iter()
+                    .map(|(s, l, g)| Row::from([("subject".to_string(), s), ("link".to_string(), l), ("goal".to_string(), g)]))
+                    .collect()
+            },
+            false => Table {
+                columns: variables.into_iter().map(|(_, var)| var).collect(),
+                rows: found.into_iter()
+                    .filter_map(|fact| bind_triplet(&fact, &(s.clone(), l.clone(), g.clone())))
+                    .map(|bindings| bindings.into_iter().map(|(var, val)| (var[1..].to_string(), val)).collect())
+                    .collect()
+            }
+        }
+    }
+
+    // the row completed by each fact matching the pattern once its variables are replaced
+    // by their values in the row, read from the index of the bound positions
+    fn probe(&self, pattern: &[Term; 3], row: &Row) -> Vec<Row> {
+        let bound = pattern.clone().map(|term| match term {
+            Term::Var(var) => row.get(&var).cloned().map_or(Term::Var(var), Term::Const),
+            term => term
+        });
+        let [s, l, g] = bound.clone().map(|term| term_pattern(&term));
+        self.facts.borrow().scan(bound[0].constant(), bound[1].constant(), bound[2].constant()).iter()
+            .filter_map(|fact| bind_triplet(fact, &(s.clone(), l.clone(), g.clone())))
+            .map(|bindings| row.clone().into_iter()
+                 .chain(bindings.into_iter().map(|(var, val)| (var[1..].to_string(), val)))
+                 .collect())
+            .collect()
+    }
+
+    // the rows of a probeable plan agreeing with a row, merged with it
+    fn probe_plan(&self, plan: &Plan, row: &Row) -> Vec<Row> {
+        match plan {
+            Plan::Scan(pattern) => self.probe(pattern, row),
+            Plan::AntiScan(pattern) => match self.probe(pattern, row).is_empty() {
+                true => vec![row.clone()],
+                false => vec![]
+            },
+            Plan::Filter(plan, comparisons) => self.probe_plan(plan, row).into_iter()
+                .filter(|row| comparisons.iter().all(|comp| comp.holds(|column| row.get(column).cloned())))
+                .collect(),
+            _ => vec![]
+        }
+    }
+
+    // the rows of one side probe the indexes for the other side when it's a scan,
+    // the tables are joined on their shared columns otherwise
+    fn join(&self, a: &Plan, b: &Plan) -> Table {
+        let columns = join_columns(&a.columns(), &b.columns());
+        let (outer, inner) = match (probeable(b, &a.columns()), probeable(a, &b.columns())) {
+            (true, _) => (a, b),
+            (false, true) => (b, a),
+            (false, false) => return join(self.evaluate(a), self.evaluate(b))
+        };
+        Table {
+            columns,
+            rows: self.evaluate(outer).rows.iter().flat_map(|row| self.probe_plan(inner, row)).collect()
+        }
+    }
+
+    // the values of the facts which don't complete the pattern
+    fn anti_scan(&self, pattern: &[Term; 3]) -> Table {
+        let variables = pattern_variables(pattern);
+        let domains = variables.iter()
+            .map(|(position, _)| self.facts.borrow().domain(*position))
+            .collect::<Vec<_>>();
+        let candidates = variables.iter().zip(domains).fold(vec![Row::new()], |rows, ((_, var), domain)|
+            rows.into_iter()
+                .flat_map(|row| domain.iter()
+                          .map(move |value| { let mut row = row.clone(); row.insert(var.clone(), value.clone()); row }))
+                .collect());
+        Table {
+            columns: variables.iter().map(|(_, var)| var.clone()).collect(),
+            rows: candidates.iter().flat_map(|row| self.probe_plan(&Plan::AntiScan(pattern.clone()), row)).collect()
+        }
+    }
+
+    fn evaluate(&self, plan: &Plan) -> Table {
+        match plan {
+            Plan::Scan(pattern) => self.scan(pattern),
+            Plan::AntiScan(pattern) => self.anti_scan(pattern),
+            Plan::Join(a, b) => self.join(a, b),
+            Plan::Filter(plan, comparisons) => {
+                let table = self.evaluate(plan);
+                Table {
+                    rows: table.rows.into_iter()
+                        .filter(|row| comparisons.iter().all(|comp| comp.holds(|column| row.get(column).cloned())))
+                        .collect(),
+                    ..table
+                }
+            },
+            Plan::Project(input, _) => Table { columns: plan.columns(), ..self.evaluate(input) }
+        }
+    }
+
+    fn query(&self, vars: &[Var], triplets: &[Triplet], comps: &[Comp]) -> DataFrame {
+        match plan_query(vars, triplets, comps, &self.statistics()) {
+            Ok(plan) => table_to_dataframe(&self.evaluate(&plan), &plan.columns()),
+            Err(_) => DataFrame::new()
+        }
+    }
+}
+
+impl Command<DataFrame> for MemoryKnowledge {
+    fn get_all(&self) -> DataFrame {
+        self.query(&[Var("A".to_string()), Var("B".to_string()), Var("C".to_string())],
+                   &[Triplet::Tvvv("A".to_string(), "B".to_string(), "C".to_string())], &[])
+    }
+
+    fn execute_command(&self, subcmd: &PredicatAST) -> Result<DataFrame, String> {
+        let cmd = self.store_to_cache(subcmd);
+        match &cmd {
+            PredicatAST::Query((vars, triplets, comps)) => Ok(self.query(vars, triplets, comps)),
+            PredicatAST::ExplainPlan(query) => match &**query {
+                PredicatAST::Query((vars, triplets, comps)) => plan_query(vars, triplets, comps, &self.statistics())
+                    .map(|plan| plan_to_dataframe(&plan, &self.statistics())),
+                _ => Err("Only a query can be explained".to_string())
+            },
+            PredicatAST::AddModifier(triplets) => {
+                triplets.iter().for_each(|tri| self.facts.borrow_mut().insert(&to_fact(tri)));
+                Ok(DataFrame::new())
+            },
+            PredicatAST::DeleteModifier(triplets) => {
+                triplets.iter().for_each(|tri| self.facts.borrow_mut().remove(&to_fact(tri)));
+                Ok(DataFrame::new())
+            },
+            PredicatAST::Infer(..) => RuleRecord::from_infer(&cmd).iter()
+                .try_for_each(|rule| self.store_rule(rule).map(|_| ()))
+                .map(|_| DataFrame::new()),
+            PredicatAST::Assert(query) => self.execute_command(query),
+            PredicatAST::Rules => Ok(rules_to_dataframe(&self.get_rules())),
+            PredicatAST::DropRule(key) => self.drop_rule(key),
+            PredicatAST::EnableRule(key) => self.set_rule_state(key, true),
+            PredicatAST::DisableRule(key) => self.set_rule_state(key, false),
+            _ => Ok(DataFrame::new())
+        }
+    }
+
+    // the facts are matched at once, the rows being built as they are read
+    fn stream(&self, query: &PredicatAST) -> Result<RowStream<'_>, String> {
+        match query {
+            PredicatAST::Query((vars, triplets, comps)) => {
+                let plan = plan_query(vars, triplets, comps, &self.statistics())?;
+                let columns = plan.columns();
+                let names = columns.iter().map(|x| &x[..]).collect::<Vec<_>>();
+                let table = self.evaluate(&plan);
+                let rows = table.rows.into_iter()
+                    .map(move |row| Ok(table.columns.iter().map(|col| row.get(col).cloned().unwrap_or_default()).collect()));
+                Ok(RowStream::new(&names, rows))
+            },
+            _ => Ok(RowStream::from(self.execute_command(query)?))
+        }
+    }
+
+    fn explain(&self, cmd: &PredicatAST) -> Result<Vec<String>, String> {
+        match cmd {
+            PredicatAST::Query((vars, triplets, comps)) => plan_query(vars, triplets, comps, &self.statistics())
+                .map(|plan| plan.describe()),
+            PredicatAST::ExplainPlan(query) | PredicatAST::Assert(query) => self.explain(query),
+            PredicatAST::Empty => Err("The AST is empty".to_string()),
+            cmd => Ok(vec![String::from(cmd.clone())])
+        }
+    }
+
+    fn validate(&self, cmd: &PredicatAST) -> Result<Vec<String>, String> {
+        match cmd {
+            PredicatAST::Infer(name, (mo, tri), conclusion) =>
+                validate_rule(name, mo, tri, conclusion, &self.get_rules()),
+            PredicatAST::Query((vars, triplets, comps)) =>
+                plan_query(vars, triplets, comps, &Statistics::default()).map(|_| vec![]),
+            PredicatAST::ExplainPlan(query) | PredicatAST::Assert(query) => self.validate(query),
+            _ => Ok(vec![])
+        }
+    }
+
+    fn infer_command_from_triplet(&self, modifier: &str, tri: &Triplet) -> Vec<String> {
+        let fact = tri.to_tuple();
+        self.rules.borrow().iter()
+            .filter(|rule| rule.enabled && rule.modifier == modifier)
+            .filter_map(|rule| bind_triplet(&fact, &rule.trigger())
+                        .and_then(|bindings| instantiate(&rule.conclusion, &bindings)))
+            .map(String::from)
+            .collect()
+    }
+}
+
+impl FactManager for MemoryKnowledge {
+    fn save_facts(&self, modifier: &str, subject: &str, link: &str, goal: &str) {
+        self.cache.borrow_mut().insert(format!("{} {} {} {}", modifier, subject, link, goal));
+    }
+
+    fn clear_facts(&self) {
+        *self.facts.borrow_mut() = Facts::default();
+    }
+
+    fn get_links(&self) -> Vec<String> {
+        self.facts.borrow().domain(1)
+    }
+
+    fn count_facts(&self) -> usize {
+        self.facts.borrow().len()
+    }
+
+    fn bulk_insert(&self, facts: &mut dyn Iterator<Item = Fact>, progress: &mut dyn FnMut(usize)) -> Result<usize, String> {
+        let mut store = self.facts.borrow_mut();
+        let mut count = 0;
+        for fact in facts {
+            store.insert(&fact);
+            count += 1;
+            progress(count);
+        }
+        Ok(count)
+    }
+
+    // the pos index holds the subjects of every goal of a link
+    fn statistics(&self) -> Statistics {
+        let facts = self.facts.borrow();
+        Statistics {
+            facts: facts.len(),
+            subjects: facts.spo.len(),
+            goals: facts.osp.len(),
+            links: facts.pos.iter()
+                .map(|(link, goals)| (link.clone(), LinkStatistics {
+                    facts: goals.values().map(BTreeSet::len).sum(),
+                    subjects: goals.values().flatten().collect::<BTreeSet<_>>().len(),
+                    goals: goals.len()
+                }))
+                .collect()
+        }
+    }
+}
+
+impl Cache for MemoryKnowledge {
+    fn clear_cache(&self) {
+        self.cache.borrow_mut().clear();
+    }
+
+    fn in_cache(&self, cmd: &PredicatAST) -> bool {
+        self.cache.borrow().contains(&String::from(cmd.clone()))
+    }
+
+    fn store_to_cache(&self, modifier: &PredicatAST) -> PredicatAST {
+        self.cache.borrow_mut().insert(String::from(modifier.clone()));
+        modifier.clone()
+    }
+}
+
+impl RuleManager<DataFrame> for MemoryKnowledge {
+    fn clear_rules(&self) {
+        self.rules.borrow_mut().clear();
+    }
+
+    fn store_rule(&self, rule: &RuleRecord) -> Result<DataFrame, String> {
+        let id = *self.next_rule_id.borrow() + 1;
+        *self.next_rule_id.borrow_mut() = id;
+        self.rules.borrow_mut().push(Rule {
+            id,
+            name: rule.name.clone(),
+            modifier: rule.modifier.get_string(),
+            premise: rule.premise.clone(),
+            conclusion: rule.conclusion.clone(),
+            enabled: true
+        });
+        Ok(DataFrame::new())
+    }
+
+    fn get_rules(&self) -> Vec<Rule> {
+        self.rules.borrow().clone()
+    }
+
+    fn drop_rule(&self, key: &str) -> Result<DataFrame, String> {
+        let mut rules = self.rules.borrow_mut();
+        let has_key = rule_has_key(&rules, key);
+        let count = rules.len();
+        rules.retain(|rule| !has_key(rule));
+        match rules.len() < count {
+            true => Ok(DataFrame::new()),
+            false => Err(format!("There is no rule named '{}'", key))
+        }
+    }
+
+    fn set_rule_state(&self, key: &str, enabled: bool) -> Result<DataFrame, String> {
+        let mut rules = self.rules.borrow_mut();
+        let has_key = rule_has_key(&rules, key);
+        let mut changed = rules.iter_mut().filter(|rule| has_key(rule)).peekable();
+        if changed.peek().is_none() {
+            return Err(format!("There is no rule named '{}'", key));
+        }
+        changed.for_each(|rule| rule.enabled = enabled);
+        Ok(DataFrame::new())
+    }
+}
+
+// the key is a rule name, or an id when no rule has this name
+fn rule_has_key(rules: &[Rule], key: &str) -> impl Fn(&Rule) -> bool {
+    let by_name = rules.iter().any(|rule| rule.name.as_deref() == Some(key));
+    let key = key.to_string();
+    move |rule| match by_name {
+        true => rule.name.as_deref() == Some(key.as_str()),
+        false => rule.id.to_string() == key
+    }
+}
+
+impl Knowledgeable<DataFrame> for MemoryKnowledge {
+    fn new() -> MemoryKnowledge {
+        MemoryKnowledge::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::parse_command;
+
+    fn run(knowledge: &MemoryKnowledge, cmd: &str) -> DataFrame {
+        knowledge.execute_command(&parse_command(cmd)[0]).unwrap()
+    }
+
+    fn df(v: &[(&str, &str)]) -> DataFrame {
+        v.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect::<Vec<_>>()
+            .try_into().unwrap()
+    }
+
+    #[test]
+    fn test_indexes() {
+        let knowledge = MemoryKnowledge::new();
+        run(&knowledge, "add pierre ami julie and julie ami emy and emy voisin pierre");
+        assert_eq!(knowledge.count_facts(), 3);
+        assert_eq!(knowledge.get_links(), vec!["ami".to_string(), "voisin".to_string()]);
+        assert_eq!(
+            run(&knowledge, "get $A where $A ami julie"),
+            df(&[("A", "pierre")]));
+        assert_eq!(
+            run(&knowledge, "get $A where emy voisin $A"),
+            df(&[("A", "pierre")]));
+        assert_eq!(
+            run(&knowledge, "get $A where $A $B pierre"),
+            df(&[("A", "emy")]));
+        run(&knowledge, "delete pierre ami julie");
+        assert_eq!(knowledge.count_facts(), 2);
+        assert_eq!(run(&knowledge, "get $A where $A ami julie"), DataFrame::with_schema(&["$A"]));
+    }
+
+    #[test]
+    fn test_rule_key() {
+        let knowledge = MemoryKnowledge::new();
+        run(&knowledge, "infer add $A ami $B -> add $B ami $A");
+        run(&knowledge, "infer add $A voisin $B -> add $B voisin $A");
+        let id = knowledge.get_rules()[1].id.to_string();
+        run(&knowledge, &format!("infer {}: add $A cousin $B -> add $B cousin $A", id));
+        assert!(knowledge.drop_rule(&id).is_ok());
+        assert_eq!(knowledge.get_rules().len(), 2);
+        assert_eq!(knowledge.get_rules()[1].id.to_string(), id);
+        assert!(knowledge.set_rule_state(&id, false).is_ok());
+        assert!(knowledge.drop_rule(&id).is_ok());
+        assert_eq!(knowledge.get_rules().len(), 1);
+        assert_eq!(knowledge.drop_rule(&id), Err(format!("There is no rule named '{}'", id)));
+        assert!(knowledge.set_rule_state("nosuch", true).is_err());
+    }
+
+    #[test]
+    fn test_bulk_load_by_chunks() {
+        let knowledge = MemoryKnowledge::new();
+        run(&knowledge, "infer add $A ami $B -> add $B ami $A");
+        let count = crate::base_knowledge::INFERENCE_CHUNK + 1;
+        let mut facts = (0..count).map(|i| (format!("p{}", i), "ami".to_string(), "julie".to_string()));
+        assert_eq!(knowledge.bulk_load(&mut facts, &mut |_| (), true), Ok((count, count)));
+        assert_eq!(knowledge.count_facts(), 2 * count);
+        assert_eq!(run(&knowledge, &format!("get $A where julie $A p{}", count - 1)), df(&[("A", "ami")]));
+    }
+
+    #[test]
+    fn test_query_join() {
+        let knowledge = MemoryKnowledge::new();
+        run(&knowledge, "add pierre ami julie and julie ami emy and emy ami paul");
+        assert_eq!(
+            run(&knowledge, "get $A $C where $A ami $B and $B ami $C"),
+            df(&[("A", "pierre"), ("C", "emy"), ("A", "julie"), ("C", "paul")]));
+    }
+
+    #[test]
+    fn test_query_negation_and_comparison() {
+        let knowledge = MemoryKnowledge::new();
+        run(&knowledge, "add pierre age 24 and julie age 17 and julie ami pierre");
+        assert_eq!(
+            run(&knowledge, "get $A where $A age $B and $B > 18"),
+            df(&[("A", "pierre")]));
+        assert_eq!(
+            run(&knowledge, "get $A where $A not ami pierre"),
+            df(&[("A", "pierre")]));
+        run(&knowledge, "add pierre ami julie and pierre ami anne and anne ami pierre and pierre ami emy");
+        assert_eq!(
+            run(&knowledge, "get $A $B where $A ami $B and $B not ami $A"),
+            df(&[("A", "pierre"), ("B", "emy")]));
+        assert_eq!(
+            run(&knowledge, "get $A where $A not ami julie and $A age 24"),
+            DataFrame::with_schema(&["$A"]));
+    }
+
+    #[test]
+    fn test_stream() {
+        let knowledge = MemoryKnowledge::new();
+        run(&knowledge, "add pierre ami julie and julie ami emy");
+        let query = &parse_command("get $C $A where $A ami $C")[0];
+        let stream = knowledge.stream(query).unwrap();
+        assert_eq!(stream.get_variables(), vec![Var::new("C"), Var::new("A")]);
+        assert_eq!(stream.into_dataframe(), Ok(run(&knowledge, "get $C $A where $A ami $C")));
+    }
+}
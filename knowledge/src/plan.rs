@@ -0,0 +1,476 @@
+use std::collections::HashMap;
+use base_context::context_traits::Var;
+use base_context::simple_context::DataFrame;
+use parser::base_parser::{Comp, Triplet};
+
+static POSITIONS: [&str; 3] = ["subject", "link", "goal"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    Var(String),
+    Const(String)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Column(String),
+    Text(String),
+    Number(f64)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub left: Value,
+    pub operator: Operator,
+    pub right: Value
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LinkStatistics {
+    pub facts: usize,
+    pub subjects: usize,
+    pub goals: usize
+}
+
+// the fact counts the planner estimates the size of the patterns with
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Statistics {
+    pub facts: usize,
+    pub subjects: usize,
+    pub goals: usize,
+    pub links: HashMap<String, LinkStatistics>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Plan {
+    // the facts matching a pattern, a column by variable or subject, link and goal without variables
+    Scan([Term; 3]),
+    // the values of the facts for which the pattern doesn't hold
+    AntiScan([Term; 3]),
+    Join(Box<Plan>, Box<Plan>),
+    Filter(Box<Plan>, Vec<Comparison>),
+    Project(Box<Plan>, Vec<String>)
+}
+
+impl Term {
+    fn from_element(element: &str) -> Term {
+        match element.strip_prefix('$') {
+            Some(var) => Term::Var(var.to_string()),
+            None => Term::Const(element.to_string())
+        }
+    }
+
+    pub fn constant(&self) -> Option<&str> {
+        match self {
+            Term::Const(c) => Some(c),
+            Term::Var(_) => None
+        }
+    }
+}
+
+pub fn pattern_of(tri: &Triplet) -> [Term; 3] {
+    let (s, l, g) = tri.to_tuple_with_variable();
+    [Term::from_element(&s), Term::from_element(&l), Term::from_element(&g)]
+}
+
+// the variables of a pattern with the first position they appear at
+pub fn pattern_variables(pattern: &[Term; 3]) -> Vec<(usize, String)> {
+    let mut variables: Vec<(usize, String)> = vec![];
+    pattern.iter().enumerate().for_each(|(position, term)| {
+        if let Term::Var(var) = term {
+            if !variables.iter().any(|(_, x)| x == var) {
+                variables.push((position, var.clone()));
+            }
+        }
+    });
+    variables
+}
+
+pub fn position_name(position: usize) -> &'static str {
+    POSITIONS[position]
+}
+
+impl Operator {
+    fn parse(s: &str) -> Result<Operator, String> {
+        match s {
+            "==" => Ok(Operator::Eq),
+            "!=" => Ok(Operator::Ne),
+            "<" => Ok(Operator::Lt),
+            "<=" => Ok(Operator::Le),
+            ">" => Ok(Operator::Gt),
+            ">=" => Ok(Operator::Ge),
+            op => Err(format!("The operator '{}' isn't supported", op))
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Operator::Eq => "==",
+            Operator::Ne => "!=",
+            Operator::Lt => "<",
+            Operator::Le => "<=",
+            Operator::Gt => ">",
+            Operator::Ge => ">="
+        }
+    }
+
+    fn selectivity(&self) -> f64 {
+        match self {
+            Operator::Eq => 0.1,
+            Operator::Ne => 0.9,
+            _ => 1.0 / 3.0
+        }
+    }
+
+    fn holds(&self, ordering: std::cmp::Ordering) -> bool {
+        match self {
+            Operator::Eq => ordering.is_eq(),
+            Operator::Ne => ordering.is_ne(),
+            Operator::Lt => ordering.is_lt(),
+            Operator::Le => ordering.is_le(),
+            Operator::Gt => ordering.is_gt(),
+            Operator::Ge => ordering.is_ge()
+        }
+    }
+}
+
+impl Statistics {
+    // the facts of the link spread evenly over its subjects and goals
+    fn pattern_rows(&self, pattern: &[Term; 3]) -> f64 {
+        let link = match pattern[1].constant() {
+            Some(link) => self.links.get(link).cloned().unwrap_or_default(),
+            None => LinkStatistics { facts: self.facts, subjects: self.subjects, goals: self.goals }
+        };
+        let subjects = match pattern[0].constant() {
+            Some(_) => link.subjects.max(1) as f64,
+            None => 1.0
+        };
+        let goals = match pattern[2].constant() {
+            Some(_) => link.goals.max(1) as f64,
+            None => 1.0
+        };
+        link.facts as f64 / subjects / goals
+    }
+
+    fn domain(&self, position: usize) -> f64 {
+        match position {
+            0 => self.subjects as f64,
+            1 => self.links.len() as f64,
+            _ => self.goals as f64
+        }
+    }
+}
+
+impl Value {
+    fn parse(s: &str) -> Value {
+        match (s.strip_prefix('$'), s.parse::<f64>()) {
+            (Some(var), _) => Value::Column(var.to_string()),
+            (None, Ok(number)) => Value::Number(number),
+            (None, Err(_)) => Value::Text(s.strip_prefix('\'').and_then(|s| s.strip_suffix('\''))
+                                         .map(|s| s.replace("''", "'")).unwrap_or(s.to_string()))
+        }
+    }
+
+    fn resolve(&self, row: &impl Fn(&str) -> Option<String>) -> Option<String> {
+        match self {
+            Value::Column(column) => row(column),
+            Value::Text(text) => Some(text.clone()),
+            Value::Number(number) => Some(number.to_string())
+        }
+    }
+
+    pub fn display(&self) -> String {
+        match self {
+            Value::Column(column) => format!("${}", column),
+            Value::Text(text) => format!("'{}'", text),
+            Value::Number(number) => number.to_string()
+        }
+    }
+}
+
+impl TryFrom<&Comp> for Comparison {
+    type Error = String;
+
+    fn try_from(Comp(comp): &Comp) -> Result<Self, Self::Error> {
+        match comp.trim().splitn(3, ' ').collect::<Vec<_>>()[..] {
+            [left, operator, right] => Ok(Comparison {
+                left: Value::parse(left),
+                operator: Operator::parse(operator)?,
+                right: Value::parse(right)
+            }),
+            _ => Err(format!("The comparison '{}' is malformed", comp.trim()))
+        }
+    }
+}
+
+impl Comparison {
+    // numbers are compared as numbers, anything else as text
+    pub fn holds(&self, row: impl Fn(&str) -> Option<String>) -> bool {
+        let (left, right) = match (self.left.resolve(&row), self.right.resolve(&row)) {
+            (Some(left), Some(right)) => (left, right),
+            _ => return false
+        };
+        let ordering = match (left.parse::<f64>(), right.parse::<f64>()) {
+            (Ok(l), Ok(r)) => l.partial_cmp(&r),
+            _ => Some(left.cmp(&right))
+        };
+        ordering.is_some_and(|o| self.operator.holds(o))
+    }
+
+    pub fn display(&self) -> String {
+        format!("{} {} {}", self.left.display(), self.operator.symbol(), self.right.display())
+    }
+
+    fn columns(&self) -> Vec<&str> {
+        [&self.left, &self.right].into_iter()
+            .filter_map(|value| match value {
+                Value::Column(column) => Some(column.as_str()),
+                _ => None
+            })
+            .collect()
+    }
+}
+
+impl Plan {
+    pub fn columns(&self) -> Vec<String> {
+        match self {
+            Plan::Scan(pattern) if pattern_variables(pattern).is_empty() =>
+                POSITIONS.iter().map(|x| x.to_string()).collect(),
+            Plan::Scan(pattern) | Plan::AntiScan(pattern) =>
+                pattern_variables(pattern).into_iter().map(|(_, var)| var).collect(),
+            Plan::Join(a, b) => {
+                let left = a.columns();
+                let right = b.columns().into_iter().filter(|x| !left.contains(x)).collect::<Vec<_>>();
+                left.into_iter().chain(right).collect()
+            },
+            Plan::Filter(plan, _) => plan.columns(),
+            Plan::Project(plan, columns) if columns.is_empty() => plan.columns(),
+            Plan::Project(_, columns) => columns.clone()
+        }
+    }
+
+    // the number of rows the plan should give back
+    pub fn estimate(&self, stats: &Statistics) -> f64 {
+        match self {
+            Plan::Scan(pattern) => stats.pattern_rows(pattern),
+            Plan::AntiScan(pattern) => {
+                let candidates = pattern_variables(pattern).iter()
+                    .map(|(position, _)| stats.domain(*position))
+                    .product::<f64>();
+                (candidates - stats.pattern_rows(pattern)).max(0.0)
+            },
+            Plan::Join(a, b) => {
+                let left = a.columns();
+                match b.columns().iter().any(|x| left.contains(x)) {
+                    true => a.estimate(stats).min(b.estimate(stats)),
+                    false => a.estimate(stats) * b.estimate(stats)
+                }
+            },
+            Plan::Filter(plan, comparisons) => comparisons.iter()
+                .fold(plan.estimate(stats), |rows, comp| rows * comp.operator.selectivity()),
+            Plan::Project(plan, _) => plan.estimate(stats)
+        }
+    }
+
+    fn operator(&self) -> String {
+        let pattern = |p: &[Term; 3]| p.iter().map(|term| match term {
+                Term::Var(var) => format!("${}", var),
+                Term::Const(c) => c.clone()
+            }).collect::<Vec<_>>().join(" ");
+        match self {
+            Plan::Scan(p) => format!("scan {}", pattern(p)),
+            Plan::AntiScan(p) => format!("scan not {}", pattern(p)),
+            Plan::Join(..) => "join".to_string(),
+            Plan::Filter(_, comparisons) =>
+                format!("filter {}", comparisons.iter().map(Comparison::display).collect::<Vec<_>>().join(" and ")),
+            Plan::Project(_, columns) => format!("project {}", match columns.is_empty() {
+                    true => "*".to_string(),
+                    false => columns.iter().map(|x| format!("${}", x)).collect::<Vec<_>>().join(" ")
+                })
+        }
+    }
+
+    fn inputs(&self) -> Vec<&Plan> {
+        match self {
+            Plan::Scan(_) | Plan::AntiScan(_) => vec![],
+            Plan::Join(a, b) => vec![a, b],
+            Plan::Filter(plan, _) | Plan::Project(plan, _) => vec![plan]
+        }
+    }
+
+    // one line by operator with its estimated rows, the inputs being indented under it
+    pub fn explain(&self, stats: &Statistics) -> Vec<(String, f64)> {
+        let inputs = self.inputs().into_iter()
+            .flat_map(|plan| plan.explain(stats))
+            .map(|(line, rows)| (format!("  {}", line), rows));
+        [(self.operator(), self.estimate(stats))].into_iter().chain(inputs).collect()
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        self.explain(&Statistics::default()).into_iter().map(|(line, _)| line).collect()
+    }
+}
+
+// the lines of the explained plan with their estimated rows
+pub fn plan_to_dataframe(plan: &Plan, stats: &Statistics) -> DataFrame {
+    plan.explain(stats).into_iter().enumerate()
+        .flat_map(|(step, (operator, rows))| [
+                  ("step".to_string(), (step + 1).to_string()),
+                  ("operator".to_string(), operator),
+                  ("rows".to_string(), format!("{:.0}", rows.ceil()))])
+        .collect::<Vec<_>>()
+        .try_into().unwrap_or_default()
+}
+
+fn scan(tri: &Triplet) -> Plan {
+    let negated = *tri != tri.clone().invert();
+    match negated {
+        true => Plan::AntiScan(pattern_of(tri)),
+        false => Plan::Scan(pattern_of(tri))
+    }
+}
+
+// the scans from the smallest one, a scan sharing a variable with the joined ones comes before the others
+fn order_by_selectivity(scans: Vec<Plan>, stats: &Statistics) -> Vec<Plan> {
+    let mut remaining = scans;
+    let mut ordered: Vec<Plan> = vec![];
+    while !remaining.is_empty() {
+        let bound = ordered.iter().flat_map(Plan::columns).collect::<Vec<_>>();
+        let cost = |plan: &Plan| (!bound.is_empty() && !plan.columns().iter().any(|x| bound.contains(x)),
+                                  plan.estimate(stats));
+        let next = remaining.iter().enumerate()
+            .min_by(|(_, a), (_, b)| cost(a).partial_cmp(&cost(b)).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        ordered.push(remaining.remove(next));
+    }
+    ordered
+}
+
+// filters the plan with the pending comparisons its columns are enough for
+fn push_comparisons(plan: Plan, pending: &mut Vec<Comparison>) -> Plan {
+    let columns = plan.columns();
+    let (ready, rest): (Vec<_>, Vec<_>) = pending.drain(..)
+        .partition(|comp| comp.columns().iter().all(|x| columns.iter().any(|c| c == x)));
+    *pending = rest;
+    match ready.is_empty() {
+        true => plan,
+        false => Plan::Filter(Box::new(plan), ready)
+    }
+}
+
+// get [vars] where [triplets] and [comparisons]
+pub fn plan_query(vars: &[Var], triplets: &[Triplet], comps: &[Comp], stats: &Statistics) -> Result<Plan, String> {
+    let mut pending = comps.iter()
+        .map(Comparison::try_from)
+        .collect::<Result<Vec<_>, String>>()?;
+    let scans = match triplets.iter().filter(|tri| **tri != Triplet::Empty).map(scan).collect::<Vec<_>>() {
+        scans if scans.is_empty() => vec![Plan::Scan(POSITIONS.map(|x| Term::Var(x.to_string())))],
+        scans => order_by_selectivity(scans, stats)
+    };
+    let mut source: Option<Plan> = None;
+    for scan in scans {
+        let scan = push_comparisons(scan, &mut pending);
+        source = Some(match source {
+            Some(joined) => push_comparisons(Plan::Join(Box::new(joined), Box::new(scan)), &mut pending),
+            None => scan
+        });
+    }
+    // the comparisons on unknown columns are kept to empty the result
+    let source = source.expect("a query has at least one scan");
+    let filtered = match pending.is_empty() {
+        true => source,
+        false => Plan::Filter(Box::new(source), pending)
+    };
+    Ok(Plan::Project(Box::new(filtered), vars.iter().map(|Var(x)| x.trim_start_matches('$').to_string()).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tvee(a: &str, b: &str, c: &str) -> Triplet {
+        Triplet::Tvee(a.to_string(), b.to_string(), c.to_string())
+    }
+
+    fn scan_of(s: &str, l: &str, g: &str) -> Box<Plan> {
+        Box::new(Plan::Scan([s, l, g].map(Term::from_element)))
+    }
+
+    #[test]
+    fn test_plan_query() {
+        let plan = plan_query(&[Var("A".to_string())],
+                              &[tvee("A", "est", "mortel"), tvee("A", "est", "grec")],
+                              &[Comp(" $A != 'socrate'".to_string())],
+                              &Statistics::default());
+        assert_eq!(
+            plan,
+            Ok(Plan::Project(Box::new(Plan::Join(
+                Box::new(Plan::Filter(
+                    scan_of("$A", "est", "mortel"),
+                    vec![Comparison {
+                        left: Value::Column("A".to_string()),
+                        operator: Operator::Ne,
+                        right: Value::Text("socrate".to_string())}])),
+                scan_of("$A", "est", "grec"))),
+                vec!["A".to_string()])));
+        assert_eq!(plan.unwrap().columns(), vec!["A".to_string()]);
+    }
+
+    #[test]
+    fn test_plan_query_by_selectivity() {
+        let stats = Statistics {
+            facts: 1000,
+            subjects: 100,
+            goals: 400,
+            links: HashMap::from([
+                ("age".to_string(), LinkStatistics { facts: 100, subjects: 100, goals: 50 }),
+                ("ami".to_string(), LinkStatistics { facts: 300, subjects: 100, goals: 100 }),
+                ("est".to_string(), LinkStatistics { facts: 200, subjects: 100, goals: 4 })])
+        };
+        let plan = plan_query(&[],
+                              &[Triplet::Tvvv("A".to_string(), "B".to_string(), "C".to_string()),
+                                Triplet::Tvev("A".to_string(), "age".to_string(), "D".to_string()),
+                                Triplet::Tvev("C".to_string(), "ami".to_string(), "E".to_string()),
+                                tvee("A", "est", "grec")],
+                              &[Comp(" $D > 20".to_string()), Comp(" $E != $A".to_string())],
+                              &stats).unwrap();
+        assert_eq!(
+            plan.describe(),
+            vec!["project *",
+                 "  filter $E != $A",
+                 "    join",
+                 "      join",
+                 "        join",
+                 "          scan $A est grec",
+                 "          filter $D > 20",
+                 "            scan $A age $D",
+                 "        scan $A $B $C",
+                 "      scan $C ami $E"]);
+        assert_eq!(plan.estimate(&stats).round(), 30.0);
+    }
+
+    #[test]
+    fn test_comparison_types() {
+        let row = |column: &str| match column {
+            "A" => Some("9".to_string()),
+            "B" => Some("abc".to_string()),
+            _ => None
+        };
+        let holds = |comp: &str| Comparison::try_from(&Comp(comp.to_string())).unwrap().holds(row);
+        assert!(holds(" $A < 10"));
+        assert!(holds(" $B > 'abb'"));
+        assert!(!holds(" $C == 3"));
+        assert!(Comparison::try_from(&Comp(" $A ~ 3".to_string())).is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use parser::base_parser::{PredicatAST, Triplet, CommandType};
+use crate::base_knowledge::Rule;
+use crate::inference::is_variable;
+use itertools::Itertools;
+
+type Pattern = (String, String, String);
+
+struct RuleNode {
+    id: Option<i64>,
+    modifier: String,
+    trigger: Pattern,
+    conclusion: (String, Vec<Pattern>)
+}
+
+fn pattern_variables(patterns: &[Pattern]) -> Vec<String> {
+    patterns.iter()
+        .flat_map(|(s, l, g)| [s, l, g])
+        .filter(|x| is_variable(x))
+        .cloned()
+        .unique()
+        .collect()
+}
+
+fn to_patterns(triplets: &[Triplet]) -> Vec<Pattern> {
+    triplets.iter().map(Triplet::to_tuple_with_variable).collect()
+}
+
+fn conclusion_patterns(conclusion: &PredicatAST) -> Result<(String, Vec<Pattern>), String> {
+    match conclusion {
+        PredicatAST::AddModifier(tri) => Ok(("add".to_string(), to_patterns(tri))),
+        PredicatAST::DeleteModifier(tri) => Ok(("delete".to_string(), to_patterns(tri))),
+        _ => Err(format!("The conclusion '{}' isn't a single add or delete command", String::from(conclusion.clone())))
+    }
+}
+
+// rename the variables by order of appearance so that two rules only differing
+// by the name of their variables get the same representation
+fn canonical(modifier: &str, trigger: &Pattern, (conclusion_modifier, conclusion): &(String, Vec<Pattern>)) -> (String, String, Vec<Pattern>) {
+    let mut variables: Vec<String> = vec![];
+    let mut rename = |x: &String| match is_variable(x) {
+        true => {
+            let index = variables.iter().position(|y| y == x)
+                .unwrap_or_else(|| { variables.push(x.clone()); variables.len() - 1 });
+            format!("${}", index)
+        },
+        false => x.clone()
+    };
+    let patterns = std::iter::once(trigger).chain(conclusion)
+        .map(|(s, l, g)| (rename(s), rename(l), rename(g)))
+        .collect();
+    (modifier.to_string(), conclusion_modifier.clone(), patterns)
+}
+
+fn unifiable(a: &Pattern, b: &Pattern) -> bool {
+    [(&a.0, &b.0), (&a.1, &b.1), (&a.2, &b.2)].iter()
+        .all(|(x, y)| is_variable(x) || is_variable(y) || x == y)
+}
+
+fn triggers(from: &RuleNode, to: &RuleNode) -> bool {
+    from.conclusion.0 == to.modifier
+        && from.conclusion.1.iter().any(|pattern| unifiable(pattern, &to.trigger))
+}
+
+fn reachable(nodes: &[RuleNode], start: Vec<usize>, edge: impl Fn(&RuleNode, &RuleNode) -> bool) -> Vec<bool> {
+    let mut seen = vec![false; nodes.len()];
+    let mut stack = start;
+    while let Some(i) = stack.pop() {
+        if !seen[i] {
+            seen[i] = true;
+            stack.extend((0..nodes.len()).filter(|&j| edge(&nodes[i], &nodes[j])));
+        }
+    }
+    seen
+}
+
+fn neighbours(nodes: &[RuleNode], new: &[usize], edge: &impl Fn(&RuleNode, &RuleNode) -> bool) -> Vec<usize> {
+    (0..nodes.len())
+        .filter(|&j| new.iter().any(|&i| edge(&nodes[i], &nodes[j])))
+        .collect()
+}
+
+fn oscillating_cycle(name: &str, nodes: &[RuleNode], new: &[usize]) -> Option<String> {
+    let forward = |a: &RuleNode, b: &RuleNode| triggers(a, b);
+    let backward = |a: &RuleNode, b: &RuleNode| triggers(b, a);
+    let after = reachable(nodes, neighbours(nodes, new, &forward), forward);
+    let before = reachable(nodes, neighbours(nodes, new, &backward), backward);
+    let cycle = (0..nodes.len()).filter(|&i| after[i] && before[i]).collect::<Vec<_>>();
+    let modifiers = cycle.iter().map(|&i| &nodes[i].conclusion.0).unique().count();
+    match modifiers > 1 {
+        true => Some(format!(
+                "The rule '{}' forms a cycle with the rules [{}] which adds and deletes the same facts, its inference may never settle",
+                name,
+                cycle.iter().flat_map(|&i| nodes[i].id).join(", "))),
+        false => None
+    }
+}
+
+fn existing_node(rule: &Rule) -> Option<RuleNode> {
+    conclusion_patterns(&rule.conclusion).ok()
+        .map(|conclusion| RuleNode {
+            id: Some(rule.id),
+            modifier: rule.modifier.clone(),
+            trigger: rule.trigger(),
+            conclusion
+        })
+}
+
+// check an infer command against the rules already stored
+// returns the warnings of a valid rule or the reason of its rejection
+pub fn validate_rule(name: &Option<String>, modifier: &CommandType, triggers: &[Triplet], conclusion: &PredicatAST, existing: &[Rule]) -> Result<Vec<String>, String> {
+    let premise = to_patterns(triggers);
+    let conclusion_text = String::from(conclusion.clone());
+    let display_name = name.clone().unwrap_or(format!("{} {} -> {}",
+            modifier.get_string(),
+            premise.iter().map(|(s, l, g)| format!("{} {} {}", s, l, g)).join(" and "),
+            conclusion_text));
+    let (conclusion_modifier, conclusion_patterns) = conclusion_patterns(conclusion)?;
+
+    let unbound = pattern_variables(&conclusion_patterns).into_iter()
+        .filter(|var| !pattern_variables(&premise).contains(var))
+        .collect::<Vec<_>>();
+    if !unbound.is_empty() {
+        return Err(format!("The variables {} of the conclusion '{}' aren't bound by the premise",
+                           unbound.join(", "), conclusion_text));
+    }
+
+    if let Some(name) = name.as_ref().filter(|x| existing.iter().any(|rule| rule.name.as_ref() == Some(x))) {
+        return Err(format!("A rule named '{}' already exists", name));
+    }
+
+    let conclusion = (conclusion_modifier, conclusion_patterns);
+    let stored = existing.iter()
+        .flat_map(existing_node)
+        .map(|node| canonical(&node.modifier, &node.trigger, &node.conclusion))
+        .collect::<Vec<_>>();
+    if premise.iter().all(|pattern| stored.contains(&canonical(&modifier.get_string(), pattern, &conclusion))) {
+        return Err(format!("The rule '{}' duplicates an existing rule", display_name));
+    }
+
+    let mut nodes = existing.iter()
+        .filter(|rule| rule.enabled)
+        .flat_map(existing_node)
+        .collect::<Vec<_>>();
+    let new = (nodes.len()..(nodes.len() + premise.len())).collect::<Vec<_>>();
+    nodes.extend(premise.into_iter().map(|trigger| RuleNode {
+        id: None,
+        modifier: modifier.get_string(),
+        trigger,
+        conclusion: conclusion.clone()
+    }));
+    Ok(oscillating_cycle(&display_name, &nodes, &new).into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::parse_command;
+
+    fn rule(id: i64, modifier: &str, trigger: (&str, &str, &str), command: &str) -> Rule {
+        Rule {
+            id,
+            name: None,
+            modifier: modifier.to_string(),
+            premise: Triplet::from_tuple_with_variable(trigger),
+            conclusion: cmd(command),
+            enabled: true
+        }
+    }
+
+    fn cmd(command: &str) -> PredicatAST {
+        parse_command(command)[0].clone()
+    }
+
+    fn ami(a: &str, b: &str) -> Vec<Triplet> {
+        vec![Triplet::Tvev(a.to_string(), "ami".to_string(), b.to_string())]
+    }
+
+    #[test]
+    fn test_validate_rule_unbound_variable() {
+        assert!(validate_rule(&None, &CommandType::Add, &ami("A", "B"), &cmd("add $B ami $C"), &[]).is_err());
+        assert_eq!(
+            validate_rule(&None, &CommandType::Add, &ami("A", "B"), &cmd("add $B ami $A"), &[]),
+            Ok(vec![]));
+    }
+
+    #[test]
+    fn test_validate_rule_unparsable_conclusion() {
+        assert!(validate_rule(&None, &CommandType::Add, &ami("A", "B"), &cmd("get $A where $A ami $B"), &[]).is_err());
+    }
+
+    #[test]
+    fn test_validate_rule_duplicate() {
+        let existing = vec![rule(1, "add", ("$A", "ami", "$B"), "add $B ami $A")];
+        assert!(validate_rule(&None, &CommandType::Add, &ami("X", "Y"), &cmd("add $Y ami $X"), &existing).is_err());
+        assert!(validate_rule(&None, &CommandType::Add, &ami("X", "Y"), &cmd("add $X ami $Y"), &existing).is_ok());
+        let existing = vec![rule(1, "add", ("$A", "ami", "le $B"), "add $A ami 'le $B'")];
+        let trigger = |goal| vec![Triplet::from_tuple_with_variable(("$X", "ami", goal))];
+        assert!(validate_rule(&None, &CommandType::Add, &trigger("le $Y"), &cmd("add $X ami 'le $Y'"), &existing).is_ok());
+        assert!(validate_rule(&None, &CommandType::Add, &trigger("le $B"), &cmd("add $X ami 'le $B'"), &existing).is_err());
+    }
+
+    #[test]
+    fn test_validate_rule_cycle() {
+        let existing = vec![rule(1, "add", ("$A", "ami", "$B"), "delete $A ami $B")];
+        assert_eq!(
+            validate_rule(&None, &CommandType::Delete, &ami("A", "B"), &cmd("add $A ami $B"), &existing)
+                .map(|warnings| warnings.len()),
+            Ok(1));
+    }
+
+}
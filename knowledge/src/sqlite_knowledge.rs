@@ -12,7 +12,7 @@ use base_context::simple_context::SimpleContext;
 use metaprogramming::substitute_variables;
 use std::collections::HashMap;
 use super::Knowledgeable;
-use crate::base_knowledge::{Command, FactManager, Cache, RuleManager};
+use crate::base_knowledge::{Command, Translator, FactManager, Cache, RuleManager, Rule, RuleRecord, Fact, rules_to_dataframe};
 use parser::soft_predicat;
 use parser::base_parser::PredicatAST;
 use parser::base_parser::PredicatAST::{Query, AddModifier, DeleteModifier, Empty, Infer};
@@ -27,21 +27,29 @@ use itertools::izip;
 use itertools::Itertools;
 use serial_test::serial;
 use base_context::simple_context::DataFrame;
+use base_context::stream::RowStream;
 use crate::base_knowledge::Joinable;
+use crate::rule_validation::validate_rule;
+use crate::inference::{bind_triplet, instantiate};
+use crate::plan::Value as PlanValue;
+use crate::plan::{Plan, Term, Operator, Comparison, Statistics, LinkStatistics, plan_query, plan_to_dataframe, pattern_of, pattern_variables, position_name};
 use std::convert::TryFrom;
 
 #[derive(Debug)]
 pub enum Sql {
     Query(String),
-    Rule(String),
-    Modify(String)
+    Rule(RuleRecord),
+    Modify(String),
+    ListRules,
+    ExplainPlan(Plan),
+    DropRule(String),
+    SetRuleState(String, bool)
 }
 
 impl Into<Sql> for String {
     fn into(self) -> Sql {
         match &self[0..6]  {
             "SELECT" => Sql::Query(self.to_string()),
-            "%rule%" => Sql::Rule(self.to_string()),
             _ => Sql::Modify(self.to_string())
         }
     }
@@ -56,6 +64,8 @@ impl Into<Sql> for &str {
 static SUBJECT: &str = ":subject";
 static LINK: &str = ":link";
 static GOAL: &str = ":goal";
+// a rule key is its name, or its id when no rule has this name
+static RULE_KEY: &str = "CASE WHEN EXISTS (SELECT 1 FROM rules WHERE name = ?) THEN name = ? ELSE id = ? END";
 
 static CREATE_FACTS : &str = "CREATE TABLE IF NOT EXISTS facts(
                   'subject' TEXT,
@@ -99,19 +109,61 @@ static CREATE_CONTEXT : &str = "
 CREATE TABLE IF NOT EXISTS context('name' TEXT); 
 ";
 
-static CREATE_UNIQUE_INDEX_RULES : &str = "
-CREATE UNIQUE INDEX rules_body on rules (body);
-";
+static CREATE_SCHEMA_VERSION : &str = "CREATE TABLE IF NOT EXISTS schema_version(
+                            'version' INTEGER PRIMARY KEY,
+                            'description' TEXT,
+                            'applied_at' TEXT DEFAULT CURRENT_TIMESTAMP);
+                    ";
 
-static CREATE_UNIQUE_INDEX_FACTS : &str = "CREATE UNIQUE INDEX fact_subject_link_goal ON rules (subject, link, goal);";
+// the primary key of the facts already starts with the subject
+static CREATE_INDEX_FACTS_LINK : &str = "CREATE INDEX IF NOT EXISTS facts_link_goal ON facts (link, goal);";
+static CREATE_INDEX_FACTS_GOAL : &str = "CREATE INDEX IF NOT EXISTS facts_goal ON facts (goal);";
+static CREATE_INDEX_RULES_MODIFIER : &str = "CREATE INDEX IF NOT EXISTS rules_modifier ON rules (modifier, enabled);";
 
-static CREATE_UNIQUE_INDEX_HISTORICAL : &str = "
-CREATE UNIQUE INDEX historical_event ON historical (event);
-";
+enum Step {
+    Execute(&'static str),
+    // table, column and definition, skipped when an older version of the store already added it
+    AddColumn(&'static str, &'static str, &'static str)
+}
+
+struct Migration {
+    version: i64,
+    description: &'static str,
+    steps: &'static [Step]
+}
+
+// applied in order, each one in its own transaction
+static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "facts, rules and cache tables",
+        steps: &[Step::Execute(CREATE_FACTS), Step::Execute(CREATE_RULES), Step::Execute(CREATE_CACHE)]
+    },
+    Migration {
+        version: 2,
+        description: "rules can be disabled",
+        steps: &[Step::AddColumn("rules", "enabled", "INTEGER DEFAULT 1")]
+    },
+    Migration {
+        version: 3,
+        description: "rules keep their serialized premise and conclusion",
+        steps: &[Step::AddColumn("rules", "premise", "TEXT"), Step::AddColumn("rules", "conclusion", "TEXT")]
+    },
+    Migration {
+        version: 4,
+        description: "indexes on the links and goals of the facts and on the rule triggers",
+        steps: &[Step::Execute(CREATE_INDEX_FACTS_LINK),
+                 Step::Execute(CREATE_INDEX_FACTS_GOAL),
+                 Step::Execute(CREATE_INDEX_RULES_MODIFIER)]
+    },
+];
 
 static INITIALYZE_STAGE : &str = "INSERT or IGNORE INTO stage (stage) VALUES (0)";
 static INITIALYZE_CONTEXT : &str = "INSERT or IGNORE INTO context (name) VALUES ('default')";
 
+static STATISTICS : &str = "SELECT count(*) AS facts, count(DISTINCT subject) AS subjects, count(DISTINCT goal) AS goals FROM facts;";
+static LINK_STATISTICS : &str = "SELECT link, count(*) AS facts, count(DISTINCT subject) AS subjects, count(DISTINCT goal) AS goals FROM facts GROUP BY link;";
+
 pub struct SqliteKnowledge {
     connection: Connection,
 }
@@ -127,28 +179,19 @@ fn extract_columns(sql_select_query: &str) -> Vec<&str> {
 
 
 impl Joinable for DataFrame {
-    fn join(a: Self, b: Self) -> Self {
-        DataFrame::join(a, b)
+    fn join(a: Self, b: Self) -> Result<Self, String> {
+        a.join(&b).map_err(|e| e.to_string())
     }
 }
 
-impl Command<DataFrame> for SqliteKnowledge {
+impl Translator<DataFrame> for SqliteKnowledge {
     type Language = Sql;
 
+    // the columns keep the order of the select, even without any row
     fn get(&self, cmd: &str) -> DataFrame {
-        let mut v: Vec<(String, String)> = vec![];
-        let _ = self.connection.iterate(cmd, |sqlite_couple| {
-            for couple in sqlite_couple.iter() {
-                v.push((couple.0.to_string(),
-                        couple.1.unwrap_or("").to_string()));
-            }
-            true
-        });
-        DataFrame::try_from(v).unwrap()
-    }
-
-    fn get_all(&self) -> DataFrame {
-        self.get(&"SELECT A,B,C from (SELECT subject as A, link as B, goal as C FROM facts)")
+        self.rows(cmd)
+            .and_then(RowStream::into_dataframe)
+            .unwrap_or_default()
     }
 
     fn modify(&self, cmd: &str) -> Result<DataFrame, &str> {
@@ -161,103 +204,89 @@ impl Command<DataFrame> for SqliteKnowledge {
 
     fn translate<'a>(&'a self, ast: &PredicatAST) -> Result<Vec<Sql>, &str> {
         match ast {
-            Query((get, link, filter)) => Ok(vec![query_to_sql(get, link, filter).into()]),
+            Query((get, link, filter)) => query_to_sql(get, link, filter, &self.statistics())
+                .map(|sql| vec![sql.into()])
+                .map_err(|_| "The query can't be planned"),
+            PredicatAST::ExplainPlan(query) => match &**query {
+                Query((get, link, filter)) => plan_query(get, link, filter, &self.statistics())
+                    .map(|plan| vec![Sql::ExplainPlan(plan)])
+                    .map_err(|_| "The query can't be planned"),
+                _ => Err("Only a query can be explained")
+            },
             AddModifier(commands) => 
                 Ok(vec![commands.iter()
                             .map(|x| triplet_to_insert(x))
-                            .fold("".to_string(), string_concat)
+                            .collect::<Vec<_>>().join("; ")
                             .into()]),
             DeleteModifier(commands) => 
                 Ok(vec![commands.iter()
                             .map(|x| triplet_to_delete(x))
-                            .fold("".to_string(), string_concat)
+                            .collect::<Vec<_>>().join("; ")
                             .into()]),
-            Infer((b, c), pre, cmd) => {
-                    // TODO : perhaps remove the match (isn't used yet)
-                    let scmd = match &cmd[0..3] { 
-                        "get" => self.translate(ast).unwrap().iter()
-                                .map(|x| {
-                                    if let Sql::Rule(rule) = x {
-                                        Some(rule.replace("'", "%single_quote%"))
-                                    } else { None }.unwrap() })
-                                .collect(),
-                        _ => cmd.clone()};
-                    let res = c.iter().map(|x| x.to_tuple_with_variable())
-                        .map(|(t1, t2, t3)| {
-                            format!("%rule%%|%{}%|%{}%|%{}%|%{}%|%{}%|%{}",
-                                       b.get_string(), t1, t2, t3, pre, cmd).into()
-                        }).collect::<Vec<_>>();
-                        Ok(res)
-                            },
+            Infer(..) => Ok(RuleRecord::from_infer(ast).into_iter().map(Sql::Rule).collect()),
+            PredicatAST::Assert(query) => self.translate(query),
+            PredicatAST::Rules => Ok(vec![Sql::ListRules]),
+            PredicatAST::DropRule(key) => Ok(vec![Sql::DropRule(key.clone())]),
+            PredicatAST::EnableRule(key) => Ok(vec![Sql::SetRuleState(key.clone(), true)]),
+            PredicatAST::DisableRule(key) => Ok(vec![Sql::SetRuleState(key.clone(), false)]),
             _ => Err("The AST is empty") 
         }
     }
 
-    fn execute(&self, s: &Sql) -> DataFrame {
-        let res = match s  {
-            Sql::Query(q) => self.get(q),
+    fn execute(&self, s: &Sql) -> Result<DataFrame, String> {
+        match s  {
+            Sql::Query(q) => Ok(self.get(q)),
             Sql::Rule(r) => self.store_rule(r),
-            Sql::Modify(m) => self.modify(m).unwrap()
-        }.clone();
-        res
+            Sql::Modify(m) => self.modify(m).map_err(str::to_string),
+            Sql::ListRules => Ok(rules_to_dataframe(&self.get_rules())),
+            Sql::ExplainPlan(plan) => Ok(plan_to_dataframe(plan, &self.statistics())),
+            Sql::DropRule(key) => self.drop_rule(key),
+            Sql::SetRuleState(key, enabled) => self.set_rule_state(key, *enabled)
+        }
     }
+}
 
-    fn is_invalid(&self, cmd: &PredicatAST) -> bool {
-        match cmd {
-            PredicatAST::Infer((mo, tri), pre, cmd) => {
-                tri.iter().map(|x| x.to_tuple_with_variable())
-                    .map(|(t1, t2, t3)| {
-                        let select = format!("SELECT * FROM Rules where modifier = {:?} subject = {:?} or link = {:?} or goal = {:?}",
-                        mo, t1, t2, t3);
-                    match self.get(&select).get_values("backed_command") {
-                        Err(_) => false,
-                        Ok(v) => v.iter()
-                            .map(|x| x.replace("%singlequote%", "'"))
-                            .any(|cmd| ! self.get(&cmd).empty())
-                        }
-                    }).any(|x| x)
-            },
-            _ => false
-        }
+impl Command<DataFrame> for SqliteKnowledge {
+    fn get_all(&self) -> DataFrame {
+        self.get(&"SELECT A,B,C from (SELECT subject as A, link as B, goal as C FROM facts)")
     }
 
+    fn execute_command(&self, subcmd: &PredicatAST) -> Result<DataFrame, String> {
+        self.translate_and_execute(subcmd)
+    }
 
-    fn infer_command_from_triplet(&self, modifier: &str, tri: &Triplet) -> Vec<String> {
-        let (sub, lin, goa) = tri.to_tuple();
-        let select = format!("SELECT * FROM rules where modifier='{}' AND (subject='{}' OR link='{}' OR goal='{}')", modifier, sub, lin, goa);
-        let rules = self.get(&select);
-        if ! rules.empty() {
-        let dataframe_of_variables = 
-            match rules.get_values2(&["modifier", "subject", "link", "goal"]) {
-            Some(df) => {
-                df.iter().map(|x| x.into_iter().collect_tuple().unwrap()) // to tuple
-                .map(|(modi, subj, link, goal)| unify_triplet((&sub, &lin, &goa), (&subj, &link, &goal)))
-                .reduce(|context1, context2| context1.join(context2))
-                .unwrap_or(SimpleContext::new())
-            },
-            None => SimpleContext::new(),
-        };
-        
-        rules.get_values("command").unwrap().iter()
-            .flat_map(|cmd| change_variables(cmd, &dataframe_of_variables))
-            .collect()
-        } else {
-            vec![]
+    fn stream(&self, query: &PredicatAST) -> Result<RowStream<'_>, String> {
+        match query {
+            Query((get, link, filter)) => self.rows(&query_to_sql(get, link, filter, &self.statistics())?),
+            _ => Ok(RowStream::from(self.execute_command(query)?))
         }
     }
 
-    fn infer_commands_from(&self, cmd: &PredicatAST) -> Vec<String> {
+    fn explain(&self, cmd: &PredicatAST) -> Result<Vec<String>, String> {
+        self.explain_translation(cmd)
+    }
+
+    fn validate(&self, cmd: &PredicatAST) -> Result<Vec<String>, String> {
         match cmd {
-            PredicatAST::AddModifier(v_of_tri) => v_of_tri.iter()
-                .flat_map(|x| self.infer_command_from_triplet("add", x))
-                .collect::<Vec<_>>(),
-            PredicatAST::DeleteModifier(v_of_tri) => v_of_tri.iter()
-                .flat_map(|x| self.infer_command_from_triplet("delete", x))
-                .collect::<Vec<_>>(),
-            _ => vec![]
+            PredicatAST::Infer(name, (mo, tri), conclusion) =>
+                validate_rule(name, mo, tri, conclusion, &self.get_rules()),
+            PredicatAST::Query((vars, triplets, comps)) =>
+                plan_query(vars, triplets, comps, &Statistics::default()).map(|_| vec![]),
+            PredicatAST::ExplainPlan(query) | PredicatAST::Assert(query) => self.validate(query),
+            _ => Ok(vec![])
         }
     }
 
+
+    fn infer_command_from_triplet(&self, modifier: &str, tri: &Triplet) -> Vec<String> {
+        let fact = tri.to_tuple();
+        self.select_rules("WHERE enabled = 1 AND modifier = ?", &[modifier]).iter()
+            .filter_map(|rule| bind_triplet(&fact, &rule.trigger())
+                        .and_then(|bindings| instantiate(&rule.conclusion, &bindings)))
+            .map(String::from)
+            .collect()
+    }
+
 }
 
 
@@ -272,6 +301,65 @@ impl FactManager for SqliteKnowledge {
         let _ = self.connection.execute("DELETE FROM facts");
     }
 
+    fn get_links(&self) -> Vec<String> {
+        self.get("SELECT DISTINCT link FROM facts ORDER BY link;")
+            .get_values("$link")
+            .unwrap_or_default()
+    }
+
+    fn count_facts(&self) -> usize {
+        self.get("SELECT count(*) AS total FROM facts;")
+            .get_values("$total")
+            .ok()
+            .and_then(|v| v.first().and_then(|x| x.parse().ok()))
+            .unwrap_or(0)
+    }
+
+    // one transaction and one prepared statement for every fact
+    fn bulk_insert(&self, facts: &mut dyn Iterator<Item = Fact>, progress: &mut dyn FnMut(usize)) -> Result<usize, String> {
+        let insert = || -> Result<usize, sqlite::Error> {
+            self.connection.execute("BEGIN TRANSACTION;")?;
+            let mut count = 0;
+            {
+                let mut statement = self.connection.prepare("INSERT or IGNORE INTO facts (subject, link, goal) VALUES (?, ?, ?)")?;
+                for (subject, link, goal) in facts {
+                    statement.reset()?;
+                    statement.bind(&[&subject[..], &link[..], &goal[..]][..])?;
+                    while statement.next()? != sqlite::State::Done {}
+                    count += 1;
+                    progress(count);
+                }
+            }
+            self.connection.execute("COMMIT;")?;
+            Ok(count)
+        };
+        insert().map_err(|e| {
+            let _ = self.connection.execute("ROLLBACK;");
+            format!("the facts couldn't be inserted ({})", e)
+        })
+    }
+
+    fn statistics(&self) -> Statistics {
+        let count = |v: &(String, String)| v.1.parse::<usize>().unwrap_or(0);
+        let totals = self.get_vec(STATISTICS);
+        let links = self.get_vec(LINK_STATISTICS).chunks(4)
+            .map(|row| (row[0].1.clone(), LinkStatistics {
+                facts: count(&row[1]),
+                subjects: count(&row[2]),
+                goals: count(&row[3])
+            }))
+            .collect();
+        match &totals[..] {
+            [facts, subjects, goals] => Statistics {
+                facts: count(facts),
+                subjects: count(subjects),
+                goals: count(goals),
+                links
+            },
+            _ => Statistics { links, ..Statistics::default() }
+        }
+    }
+
 }
 
 impl Cache for SqliteKnowledge {
@@ -280,21 +368,18 @@ impl Cache for SqliteKnowledge {
     }
 
     fn in_cache(&self, cmd: &PredicatAST) -> bool {
-        let mut res: bool = false;
-        let query = format!("SELECT * FROM cache WHERE command = '{}'", String::from(cmd.clone()));
-        let _ = self.connection.iterate(query, |sqlite_couple| {
-            if sqlite_couple.iter().len() > 0 {
-                res = true;
-            }
-            true
-        });
-        res
+        let command = String::from(cmd.clone());
+        let mut statement = match self.connection.prepare("SELECT command FROM cache WHERE command = ?") {
+            Ok(statement) => statement,
+            Err(_) => return false
+        };
+        statement.bind(&[&command[..]][..]).is_ok()
+            && matches!(statement.next(), Ok(sqlite::State::Row))
     }
 
     fn store_to_cache(&self, modifier: &PredicatAST) -> PredicatAST {
-        let query = format!("INSERT INTO cache (command) VALUES ('{}')",
-            String::from(modifier.clone()));
-        let res = &self.connection.execute(query);
+        let _ = self.execute_with("INSERT INTO cache (command) VALUES (?)",
+                                  &[&String::from(modifier.clone())]);
         modifier.clone()
     }
 
@@ -305,26 +390,31 @@ impl RuleManager<DataFrame> for SqliteKnowledge {
         let _ = self.connection.execute("DELETE FROM rules");
     }
 
-    fn store_rule(&self, s: &str) -> DataFrame {
-        let values = s.split("%|%").collect::<Vec<_>>();
-        let cmd = format!("INSERT INTO rules (modifier, subject, link, goal, command, backed_command) VALUES (\'{}\', \'{}\', \'{}\', \'{}\', \'{}\', \'{}\')",
-                    values[1], values[2], values[3], values[4], values[5], values[6]);
-        match self.connection.execute(cmd) {
-           Err(e) => {dbg!(e); DataFrame::new()}
-           _ => DataFrame::new(),
-        }
+    fn store_rule(&self, rule: &RuleRecord) -> Result<DataFrame, String> {
+        let (subject, link, goal) = rule.premise.to_tuple_with_variable();
+        let command = String::from(rule.conclusion.clone());
+        let backed_command = format!("{} {}", rule.modifier.get_string(), String::from(rule.premise.clone()));
+        let premise = serde_json::to_string(&rule.premise).expect("a triplet is serializable");
+        let conclusion = serde_json::to_string(&rule.conclusion).expect("an AST is serializable");
+        self.execute_with(
+            "INSERT INTO rules (name, modifier, subject, link, goal, command, backed_command, premise, conclusion) VALUES (NULLIF(?, ''), ?, ?, ?, ?, ?, ?, ?, ?)",
+            &[&rule.name.clone().unwrap_or_default(), &rule.modifier.get_string(), &subject, &link, &goal,
+              &command, &backed_command, &premise, &conclusion])
+            .map(|_| DataFrame::new())
+            .map_err(|e| format!("The rule couldn't be stored ({})", e))
     }
 
-    fn get_rules(&self) -> Vec<String> {
-        let query = "SELECT modifier, subject, link, goal, command, backed_command FROM rules;".to_string();
-        let mut v: Vec<String> = vec![];
-        let _ = self.connection.iterate(query, |sqlite_couple| {
-            for couple in sqlite_couple.iter() {
-                v.push(couple.1.unwrap_or("").to_string());
-            }
-            true
-        });
-        v
+    fn get_rules(&self) -> Vec<Rule> {
+        self.select_rules("ORDER BY id", &[])
+    }
+
+    fn drop_rule(&self, key: &str) -> Result<DataFrame, String> {
+        self.change_rule(&format!("DELETE FROM rules WHERE {}", RULE_KEY), &[key, key, key], key)
+    }
+
+    fn set_rule_state(&self, key: &str, enabled: bool) -> Result<DataFrame, String> {
+        let state = if enabled { "1" } else { "0" };
+        self.change_rule(&format!("UPDATE rules SET enabled = ? WHERE {}", RULE_KEY), &[state, key, key, key], key)
     }
 }
 
@@ -334,13 +424,64 @@ impl Knowledgeable<DataFrame> for SqliteKnowledge {
         let knowledge = SqliteKnowledge {
             connection: sqlite::open("data.db").unwrap(),
         };
-        let _ = knowledge.modify(&CREATE_FACTS);
-        let _ = knowledge.modify(&CREATE_RULES);
-        let _ = knowledge.modify(&CREATE_CACHE);
+        if let Err(error) = migrate(&knowledge.connection) {
+            eprintln!("error: {}", error);
+        }
         knowledge
     }
 }
 
+fn schema_version(connection: &Connection) -> Result<i64, sqlite::Error> {
+    let mut statement = connection.prepare("SELECT max(version) AS version FROM schema_version")?;
+    statement.next()?;
+    Ok(statement.read::<Option<i64>, _>("version")?.unwrap_or(0))
+}
+
+fn has_column(connection: &Connection, table: &str, column: &str) -> Result<bool, sqlite::Error> {
+    let mut statement = connection.prepare(format!("PRAGMA table_info({})", table))?;
+    while statement.next()? == sqlite::State::Row {
+        if statement.read::<String, _>("name")? == column {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+fn apply(connection: &Connection, migration: &Migration) -> Result<(), sqlite::Error> {
+    for step in migration.steps {
+        match step {
+            Step::Execute(sql) => connection.execute(sql)?,
+            Step::AddColumn(table, column, definition) if !has_column(connection, table, column)? =>
+                connection.execute(format!("ALTER TABLE {} ADD COLUMN '{}' {};", table, column, definition))?,
+            Step::AddColumn(..) => ()
+        }
+    }
+    let mut statement = connection.prepare("INSERT INTO schema_version (version, description) VALUES (?, ?)")?;
+    statement.bind((1, migration.version))?;
+    statement.bind((2, migration.description))?;
+    while statement.next()? != sqlite::State::Done {}
+    Ok(())
+}
+
+// brings the store to the last version, a failing migration is rolled back and stops the upgrade
+fn migrate(connection: &Connection) -> Result<i64, String> {
+    let current = connection.execute(CREATE_SCHEMA_VERSION)
+        .and_then(|_| schema_version(connection))
+        .map_err(|e| format!("the schema version can't be read ({})", e))?;
+    MIGRATIONS.iter()
+        .filter(|migration| migration.version > current)
+        .try_fold(current, |_, migration| {
+            connection.execute("BEGIN TRANSACTION;")
+                .and_then(|_| apply(connection, migration))
+                .and_then(|_| connection.execute("COMMIT;"))
+                .map(|_| migration.version)
+                .map_err(|e| {
+                    let _ = connection.execute("ROLLBACK;");
+                    format!("the database couldn't be migrated to version {} ({})", migration.version, e)
+                })
+        })
+}
+
 fn unify_triplet((sub1, lin1, goa1): (&str, &str, &str), (sub2, lin2, goa2): (&str, &str, &str)) -> SimpleContext {
     let res = [(sub1, sub2), (lin1, lin2), (goa1, goa2)]
         .iter()
@@ -349,6 +490,12 @@ fn unify_triplet((sub1, lin1, goa1): (&str, &str, &str), (sub2, lin2, goa2): (&s
          SimpleContext::try_from(res).unwrap()
 }
 
+// rules stored before the premise and conclusion were serialized
+fn legacy_rule(subject: &str, link: &str, goal: &str, command: &str) -> Option<(Triplet, PredicatAST)> {
+    parse_command(command).first()
+        .map(|conclusion| (Triplet::from_tuple_with_variable((subject, link, goal)), conclusion.clone()))
+}
+
 fn substitute_variable(var: &Var, val:&str, cmd: &str) -> String {
     cmd.replace(&var.0, &format!("'{}'", val)).to_string()
 }
@@ -367,19 +514,19 @@ fn change_variables(cmd: &str, context: &SimpleContext) -> Vec<String> {
 
 fn triplet_to_delete(tri: &Triplet) -> String {
     let tup = tri.to_tuple_with_variable();
-    format!("DELETE FROM facts WHERE subject='{}' AND link='{}' AND goal='{}'",
-            tup.0, tup.1, tup.2)
+    format!("DELETE FROM facts WHERE subject={} AND link={} AND goal={}",
+            quote(&tup.0), quote(&tup.1), quote(&tup.2))
 }
 
 fn triplet_to_insert(tri: &Triplet) -> String {
     let tup = tri.to_tuple_with_variable();
-    format!("INSERT or IGNORE INTO facts (subject,link,goal) VALUES ('{}','{}','{}')",
-            tup.0, tup.1, tup.2)
+    format!("INSERT or IGNORE INTO facts (subject,link,goal) VALUES ({},{},{})",
+            quote(&tup.0), quote(&tup.1), quote(&tup.2))
 }
 
 fn translate_one_ast<'a>(ast: &'a PredicatAST) -> Result<String, &'a str> {
     match ast {
-        Query((get, link, filter)) => Ok(query_to_sql(get, link, filter)),
+        Query((get, link, filter)) => query_to_sql(get, link, filter, &Statistics::default()).map_err(|_| "The query can't be planned"),
         AddModifier(commands) => 
             Ok(commands.iter()
                         .map(|x| triplet_to_insert(x))
@@ -398,6 +545,68 @@ fn string_concat(acc: String, x: String) -> String {
 
 impl SqliteKnowledge{
 
+    fn execute_with(&self, query: &str, params: &[&str]) -> Result<(), sqlite::Error> {
+        let mut statement = self.connection.prepare(query)?;
+        statement.bind(params)?;
+        while statement.next()? != sqlite::State::Done {}
+        Ok(())
+    }
+
+    // a statement on the rules of a key, which fails when no rule has it
+    fn change_rule(&self, query: &str, params: &[&str], key: &str) -> Result<DataFrame, String> {
+        self.execute_with(query, params)
+            .map_err(|e| format!("The rule '{}' can't be changed ({})", key, e))?;
+        match self.connection.change_count() {
+            0 => Err(format!("There is no rule named '{}'", key)),
+            _ => Ok(DataFrame::new())
+        }
+    }
+
+    fn select_rules(&self, condition: &str, params: &[&str]) -> Vec<Rule> {
+        let query = format!("SELECT id, name, modifier, subject, link, goal, command, enabled, premise, conclusion FROM rules {};", condition);
+        let read_rules = || -> Result<Vec<Rule>, sqlite::Error> {
+            let mut statement = self.connection.prepare(&query)?;
+            statement.bind(params)?;
+            let mut rules = vec![];
+            while statement.next()? == sqlite::State::Row {
+                let premise = statement.read::<Option<String>, _>("premise")?
+                    .and_then(|x| serde_json::from_str(&x).ok());
+                let conclusion = statement.read::<Option<String>, _>("conclusion")?
+                    .and_then(|x| serde_json::from_str(&x).ok());
+                let body = match (premise, conclusion) {
+                    (Some(premise), Some(conclusion)) => Some((premise, conclusion)),
+                    _ => legacy_rule(&statement.read::<String, _>("subject")?,
+                                     &statement.read::<String, _>("link")?,
+                                     &statement.read::<String, _>("goal")?,
+                                     &statement.read::<String, _>("command")?)
+                };
+                if let Some((premise, conclusion)) = body {
+                    rules.push(Rule {
+                        id: statement.read("id")?,
+                        name: statement.read("name")?,
+                        modifier: statement.read("modifier")?,
+                        premise,
+                        conclusion,
+                        enabled: statement.read::<Option<i64>, _>("enabled")?.unwrap_or(1) != 0
+                    });
+                }
+            }
+            Ok(rules)
+        };
+        read_rules().unwrap_or_default()
+    }
+
+    // the rows are read from the statement while the stream is consumed
+    fn rows(&self, sql: &str) -> Result<RowStream<'_>, String> {
+        let statement = self.connection.prepare(sql).map_err(|e| e.to_string())?;
+        let columns = statement.column_names().to_vec();
+        let rows = statement.into_iter()
+            .map(|row| row
+                 .map(|row| Vec::<Value>::from(row).iter().map(value_to_string).collect())
+                 .map_err(|e| e.to_string()));
+        Ok(RowStream::new(&columns.iter().map(|x| &x[..]).collect::<Vec<_>>(), rows))
+    }
+
     fn get_vec(&self, cmd: &str) -> Vec<(String, String)> {
         let query = cmd;
         let mut v: Vec<(String, String)> = vec![];
@@ -448,29 +657,22 @@ fn to_context(hm: HashMap<String, Vec<String>>, columns: Vec<&str>) -> SimpleCon
     }
 }
 
-fn query_to_sql(get: &[Var], link: &[Triplet], filter: &[Comp]) -> String {
-    let head = format_variables(get);
-    let columns = format_triplets(link); // warning, put the result into a parenthese
-    let comparisons = format_comparisons(filter);
-    format!("{}{}{}", head, columns, comparisons)
-}
-
-fn format_triplets(tri: &[Triplet]) -> String {
-    if tri == [Triplet::Empty]{
-        String::from("facts")
-    }
-    else {
-        let sql_queries = tri.iter()
-            .filter_map(|x| Some(triplet_to_sql(x)));
-        let queries = sql_queries
-            .reduce(|acc, x| format!("({}) natural join ({})", acc, x)).unwrap();
-        format!("({})", queries)
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Binary(b) => String::from_utf8_lossy(b).to_string(),
+        Value::Null => String::new()
     }
 }
 
+fn query_to_sql(get: &[Var], link: &[Triplet], filter: &[Comp], stats: &Statistics) -> Result<String, String> {
+    plan_query(get, link, filter, stats).map(|plan| format!("{};", plan_to_sql(&plan)))
+}
 
 fn format_variables(vars: &[Var]) -> String {
-    if vars == []{
+    if vars.is_empty() {
         String::from("SELECT * FROM ")
     }
     else {
@@ -485,53 +687,121 @@ fn format_variables(vars: &[Var]) -> String {
     }
 }
 
-fn format_comparisons(comp: &[Comp]) -> String {
-    if  comp == [] {
-        String::from(";")
+fn quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+fn value_to_sql(value: &PlanValue, other: &PlanValue) -> String {
+    match (value, other) {
+        (PlanValue::Column(column), PlanValue::Number(_)) => format!("CAST({} AS NUMERIC)", column),
+        (PlanValue::Column(column), _) => column.clone(),
+        (PlanValue::Text(text), _) => quote(text),
+        (PlanValue::Number(number), _) => number.to_string()
     }
-    else {
-        let comparisons = comp.iter()
-            .filter_map(|Comp(c)| Some(c.replace("$","").replace("==","=")));
-        let final_comparisons = comparisons
-            .reduce(|acc, x| format!("{} AND{}", acc, x)).unwrap();
-        format!(" WHERE{};", final_comparisons)
+}
+
+fn comparison_to_sql(comparison: &Comparison) -> String {
+    let operator = match comparison.operator {
+        Operator::Eq => "=",
+        op => op.symbol()
+    };
+    format!("{} {} {}",
+            value_to_sql(&comparison.left, &comparison.right),
+            operator,
+            value_to_sql(&comparison.right, &comparison.left))
+}
+
+// the conditions on the columns of `table` for the constants and the repeated variables of a pattern
+fn pattern_conditions(pattern: &[Term; 3], table: &str, first_positions: &dyn Fn(&str) -> String) -> Vec<String> {
+    pattern.iter().enumerate()
+        .flat_map(|(position, term)| {
+            let column = format!("{}{}", table, position_name(position));
+            match term {
+                Term::Const(c) => Some(format!("{}={}", column, quote(c))),
+                Term::Var(var) => Some(first_positions(var)).filter(|first| *first != column)
+                    .map(|first| format!("{}={}", column, first))
+            }
+        })
+        .collect()
+}
+
+fn with_conditions(query: String, conditions: &[String]) -> String {
+    match conditions.is_empty() {
+        true => query,
+        false => format!("{} WHERE {}", query, conditions.join(" AND "))
+    }
+}
+
+fn scan_to_sql(pattern: &[Term; 3]) -> String {
+    let variables = pattern_variables(pattern);
+    let columns = match variables.is_empty() {
+        true => "subject,link,goal".to_string(),
+        false => variables.iter()
+            .map(|(position, var)| format!("{} AS {}", position_name(*position), var))
+            .collect::<Vec<_>>().join(",")
+    };
+    let first_position = |var: &str| variables.iter()
+        .find(|(_, x)| x == var)
+        .map(|(position, _)| position_name(*position).to_string())
+        .unwrap_or_default();
+    with_conditions(format!("SELECT {} FROM facts", columns),
+                    &pattern_conditions(pattern, "", &first_position))
+}
+
+// every combination of the values found at the positions of the variables,
+// which doesn't complete the pattern into a fact
+fn anti_scan_to_sql(pattern: &[Term; 3]) -> String {
+    let variables = pattern_variables(pattern);
+    let domains = variables.iter()
+        .unique_by(|(_, var)| var.clone())
+        .map(|(position, var)| format!("(SELECT DISTINCT {} AS {} FROM facts)", position_name(*position), var))
+        .collect::<Vec<_>>();
+    let bound = |var: &str| format!("domains.{}", var);
+    let missing = with_conditions("SELECT 1 FROM facts n".to_string(),
+                                  &pattern_conditions(pattern, "n.", &bound));
+    match variables.is_empty() {
+        true => format!("SELECT 1 AS missing WHERE NOT EXISTS ({})", missing),
+        false => format!("SELECT * FROM (SELECT * FROM {}) domains WHERE NOT EXISTS ({})",
+                         domains.join(" natural join "), missing)
+    }
+}
+
+// the rows of a plan binding every variable of the pattern, which don't complete it into a fact
+fn bound_anti_scan_to_sql(plan: &Plan, pattern: &[Term; 3]) -> String {
+    let bound = |var: &str| format!("bound.{}", var);
+    format!("SELECT * FROM ({}) bound WHERE NOT EXISTS ({})",
+            plan_to_sql(plan),
+            with_conditions("SELECT 1 FROM facts n".to_string(), &pattern_conditions(pattern, "n.", &bound)))
+}
+
+fn binds(plan: &Plan, pattern: &[Term; 3]) -> bool {
+    let columns = plan.columns();
+    pattern_variables(pattern).iter().all(|(_, var)| columns.contains(var))
+}
+
+// compiles a logical plan into a single select statement
+fn plan_to_sql(plan: &Plan) -> String {
+    match plan {
+        Plan::Scan(pattern) => scan_to_sql(pattern),
+        Plan::AntiScan(pattern) => anti_scan_to_sql(pattern),
+        Plan::Join(a, b) => match (a.as_ref(), b.as_ref()) {
+            (plan, Plan::AntiScan(pattern)) | (Plan::AntiScan(pattern), plan) if binds(plan, pattern) =>
+                bound_anti_scan_to_sql(plan, pattern),
+            _ => format!("SELECT * FROM ({}) natural join ({})", plan_to_sql(a), plan_to_sql(b))
+        },
+        Plan::Filter(plan, comparisons) => format!("SELECT * FROM ({}) WHERE {}",
+                plan_to_sql(plan),
+                comparisons.iter().map(comparison_to_sql).collect::<Vec<_>>().join(" AND ")),
+        Plan::Project(plan, columns) => format!("{}({})",
+                format_variables(&columns.iter().map(|x| Var(x.clone())).collect::<Vec<_>>()),
+                plan_to_sql(plan))
     }
 }
 
 pub fn triplet_to_sql(tri: &Triplet) -> String {
-    match tri {
-        Teee(a,b,c) => 
-            format!("SELECT subject,link,goal FROM facts WHERE subject='{}' AND link='{}' AND goal='{}'",a,b,c),
-        Tvee(a,b,c) => 
-            format!("SELECT subject AS {} FROM facts WHERE link='{}' AND goal='{}'",a,b,c),
-        Teve(a,b,c) => 
-            format!("SELECT link AS {} FROM facts WHERE subject='{}' AND goal='{}'",b,a,c),
-        Teev(a,b,c) => 
-            format!("SELECT goal AS {} FROM facts WHERE subject='{}' AND link='{}'",c,a,b),
-        Tvve(a,b,c) => 
-            format!("SELECT subject AS {},link AS {} FROM facts WHERE goal='{}'",a,b,c),
-        Tvev(a,b,c) => 
-            format!("SELECT subject AS {},goal AS {} FROM facts WHERE link='{}'",a,c,b),
-        Tevv(a,b,c) => 
-            format!("SELECT link AS {},goal AS {} FROM facts WHERE subject='{}'",b,c,a),
-        Tvvv(a,b,c) => 
-            format!("SELECT subject AS {},link AS {},goal AS {} FROM facts",a,b,c),
-        Triplet::Empty => String::from(""),
-        TNeee(a,b,c) => 
-            format!("SELECT subject AS {}, link AS {},goal AS {} FROM facts RIGHT JOIN facts t2 ON t1.subject = t2.subject AND t1.link = t2.link AND t1.goal = t2.goal",a,b,c),
-        TNvee(a,b,c) => 
-            format!("SELECT subject AS {} FROM facts WHERE subject NOT IN (SELECT subject AS {} FROM facts WHERE link='{}' AND goal='{}')", a,a,b,c),
-        TNeve(a,b,c) => 
-            format!("SELECT link AS {} FROM facts WHERE link NOT IN (SELECT link AS {} FROM facts WHERE subject='{}' AND goal='{}')", b,b,a,c),
-        TNeev(a,b,c) => 
-            format!("SELECT goal AS {} FROM facts WHERE link NOT IN (SELECT goal AS {} FROM facts WHERE subject='{}' AND link='{}')",c,c,a,b),
-        TNvve(a,b,c) => 
-            format!("SELECT subject AS {},link AS {} FROM facts t1 WHERE goal='{}' RIGHT JOIN facts t2 ON t1.subject = t2.subject AND t1.link = t2.link",a,b,c),
-        TNvev(a,b,c) => 
-            format!("SELECT subject AS {},goal AS {} FROM facts WHERE link='{}' RIGHT JOIN facts t2 ON t1.subject = t2.subject AND t1.goal = t2.goal",a,c,b),
-        TNevv(a,b,c) => 
-            format!("SELECT link AS {},goal AS {} FROM facts WHERE subject='{}' RIGHT JOIN facts t2 ON t1.link = t2.link AND t1.goal = t2.goal",b,c,a),
-        TNvvv(a,b,c) => String::from("SELECT * FROM facts HAVING 1 = 0"),
+    match *tri != tri.clone().invert() {
+        true => anti_scan_to_sql(&pattern_of(tri)),
+        false => scan_to_sql(&pattern_of(tri))
     }
 }
 
@@ -577,6 +847,47 @@ mod tests {
     use super::Context;
     use super::*;
 
+    #[test]
+    fn test_migrate_legacy_store() {
+        let connection = sqlite::open(":memory:").unwrap();
+        connection.execute(CREATE_FACTS).unwrap();
+        connection.execute(CREATE_RULES).unwrap();
+        connection.execute("ALTER TABLE rules ADD COLUMN 'enabled' INTEGER DEFAULT 1;").unwrap();
+        connection.execute("INSERT INTO rules (modifier, subject, link, goal, command) VALUES ('add', '$A', 'ami', '$B', 'add $B ami $A')").unwrap();
+        assert_eq!(migrate(&connection), Ok(4));
+        assert_eq!(migrate(&connection), Ok(4));
+        assert!(has_column(&connection, "rules", "conclusion").unwrap());
+        let mut statement = connection.prepare("SELECT count(*) AS n FROM rules WHERE enabled = 1").unwrap();
+        statement.next().unwrap();
+        assert_eq!(statement.read::<i64, _>("n").unwrap(), 1);
+        let mut statement = connection.prepare("SELECT name FROM sqlite_master WHERE type = 'index' AND name = 'facts_link_goal'").unwrap();
+        assert_eq!(statement.next().unwrap(), sqlite::State::Row);
+    }
+
+    #[test]
+    fn test_rule_key() {
+        let knowledge = SqliteKnowledge { connection: sqlite::open(":memory:").unwrap() };
+        migrate(&knowledge.connection).unwrap();
+        knowledge.execute_command(&parse_command("infer add $A ami $B -> add $B ami $A")[0]).unwrap();
+        knowledge.execute_command(&parse_command("infer 1: add $A voisin $B -> add $B voisin $A")[0]).unwrap();
+        assert!(knowledge.set_rule_state("1", false).is_ok());
+        let states = knowledge.get_rules().iter().map(|rule| rule.enabled).collect::<Vec<_>>();
+        assert_eq!(states, [true, false]);
+        assert!(knowledge.drop_rule("1").is_ok());
+        assert_eq!(knowledge.get_rules().iter().map(|rule| rule.name.clone()).collect::<Vec<_>>(), [None]);
+        assert!(knowledge.drop_rule("1").is_ok());
+        assert!(knowledge.get_rules().is_empty());
+        assert_eq!(knowledge.drop_rule("1"), Err("There is no rule named '1'".to_string()));
+        assert!(knowledge.set_rule_state("nosuch", true).is_err());
+    }
+
+    #[test]
+    fn test_store_rule_error() {
+        let knowledge = SqliteKnowledge { connection: sqlite::open(":memory:").unwrap() };
+        let stored = knowledge.execute_command(&parse_command("infer add $A ami $B -> add $B ami $A")[0]);
+        assert!(stored.is_err_and(|e| e.starts_with("The rule couldn't be stored")));
+    }
+
     #[test]
     fn test_from_triplet_to_sql() {
         assert_eq!(
@@ -589,14 +900,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_from_plan_to_sql() {
+        assert_eq!(
+            triplet_to_sql(&Tvee("A".to_string(), "est".to_string(), "l'homme".to_string())),
+            "SELECT subject AS A FROM facts WHERE link='est' AND goal='l''homme'");
+        assert_eq!(
+            query_to_sql(&[Var("A".to_string())],
+                         &[Tvev("A".to_string(), "ami".to_string(), "B".to_string()),
+                           Tvee("B".to_string(), "age".to_string(), "24".to_string())],
+                         &[Comp(" $A != 'pierre'".to_string())],
+                         &Statistics::default()),
+            Ok("SELECT A FROM (SELECT * FROM (SELECT * FROM (SELECT subject AS A,goal AS B FROM facts WHERE link='ami') WHERE A != 'pierre') natural join (SELECT subject AS B FROM facts WHERE link='age' AND goal='24'));".to_string()));
+        assert_eq!(
+            query_to_sql(&[Var("A".to_string())],
+                         &[Tvev("A".to_string(), "ami".to_string(), "B".to_string()),
+                           TNvev("B".to_string(), "ami".to_string(), "A".to_string())],
+                         &[],
+                         &Statistics::default()),
+            Ok("SELECT A FROM (SELECT * FROM (SELECT subject AS A,goal AS B FROM facts WHERE link='ami') bound \
+                WHERE NOT EXISTS (SELECT 1 FROM facts n WHERE n.subject=bound.B AND n.link='ami' AND n.goal=bound.A));".to_string()));
+        assert_eq!(
+            triplet_to_sql(&TNvev("A".to_string(), "ami".to_string(), "B".to_string())),
+            "SELECT * FROM (SELECT * FROM (SELECT DISTINCT subject AS A FROM facts) natural join (SELECT DISTINCT goal AS B FROM facts)) domains \
+             WHERE NOT EXISTS (SELECT 1 FROM facts n WHERE n.subject=domains.A AND n.link='ami' AND n.goal=domains.B)");
+    }
+
     #[test]
     fn test_format_variables() {
         assert_eq!(
-            format_variables(&vec![Var("X".to_string()),Var("Y".to_string())]),
+            format_variables(&[Var("X".to_string()),Var("Y".to_string())]),
             "SELECT X,Y FROM "
         );
         assert_eq!(
-            format_variables(&vec![Var("X".to_string())]),
+            format_variables(&[Var("X".to_string())]),
             "SELECT X FROM "
         );
     }
@@ -2,7 +2,7 @@
 // will be use for the language (AST) manipulation
 
 use base_context::context_traits::{Context, Var};
-use base_context::simple_context::SimpleContext;
+use base_context::simple_context::{SimpleContext, DataFrameError};
 use parser::base_parser::{PredicatAST, Triplet, Comp, Language};
 
 #[derive(PartialEq, Debug, Clone, Copy)]
@@ -29,8 +29,13 @@ where
         .collect()
 }
 
+// the triplets keep their variables without the '$' of the context columns
+fn values_of(var: &str, context: &SimpleContext) -> Result<Vec<String>, DataFrameError> {
+    context.get_values(&Var::format(var))
+}
+
 fn substitute_1_var_triplet(v1: &str, ws: (&str, &str), pos: Pos, context: &SimpleContext) -> Vec<Triplet> {
-    match (context.get_values(&v1), pos) {
+    match (values_of(v1, context), pos) {
         (Err(_), Pos::First) => vec![Triplet::Tvee(v1.to_string(), ws.0.to_string(), ws.1.to_string())],
         (Err(_), Pos::Second) => vec![Triplet::Teve(ws.0.to_string(), v1.to_string(), ws.1.to_string())],
         (Err(_), Pos::Third) => vec![Triplet::Teev(ws.0.to_string(), ws.1.to_string(), v1.to_string())],
@@ -44,7 +49,7 @@ fn substitute_1_var_triplet(v1: &str, ws: (&str, &str), pos: Pos, context: &Simp
 }
 
 fn substitute_2_var_triplet(v1: &str, v2: &str, w: &str, doublepos: DoublePos, context: &SimpleContext) -> Vec<Triplet> {
-    match (context.get_values(&v1), context.get_values(&v2), doublepos) {
+    match (values_of(v1, context), values_of(v2, context), doublepos) {
         (Err(_), Err(_), DoublePos::FirstSecond) => vec![Triplet::Tvve(v1.to_string(), v2.to_string(), w.to_string())],
         (Err(_), Err(_), DoublePos::FirstThird) => vec![Triplet::Tvev(v1.to_string(), w.to_string(), v2.to_string())],
         (Err(_), Err(_), DoublePos::SecondThird) => vec![Triplet::Tevv(w.to_string(), v1.to_string(), v2.to_string())],
@@ -70,7 +75,7 @@ fn substitute_2_var_triplet(v1: &str, v2: &str, w: &str, doublepos: DoublePos, c
 }
 
 fn substitute_3_var_triplet(va1: &str, va2:&str, va3: &str, context: &SimpleContext) -> Vec<Triplet> {
-   match (context.get_values(va1), context.get_values(va2), context.get_values(va3)) {
+   match (values_of(va1, context), values_of(va2, context), values_of(va3, context)) {
        (Err(_), Err(_), Err(_)) => vec![Triplet::Tvvv(va1.to_string(), va2.to_string(), va3.to_string())],
        (Ok(v), Err(_), Err(_)) => 
            v.iter().map(|x| Triplet::Tevv( x.to_string(), va2.to_string(), va3.to_string())).collect(),
@@ -83,7 +88,7 @@ fn substitute_3_var_triplet(va1: &str, va2:&str, va3: &str, context: &SimpleCont
        (Ok(v1), Err(_), Ok(v2)) => 
            v1.iter().zip(v2.iter()).map(|(x1, x2)| Triplet::Teve(x1.to_string(), va2.to_string(), x2.to_string())).collect(),
        (Err(_), Ok(v1), Ok(v2)) => 
-           v1.iter().zip(v2.iter()).map(|(x1, x2)| Triplet::Teev(va1.to_string(), x2.to_string(), va2.to_string())).collect(),
+           v1.iter().zip(v2.iter()).map(|(x1, x2)| Triplet::Tvee(va1.to_string(), x1.to_string(), x2.to_string())).collect(),
        (Ok(v1), Ok(v2), Ok(v3)) => 
            v1.iter().zip(v2.iter()).zip(v3.iter())
            .map(|((x1, x2), x3)| (x1, x2, x3))
@@ -3,7 +3,7 @@ pub use nom::{
     character::complete::{char, alphanumeric1, space1, digit1},
     sequence::{preceded, tuple, delimited, terminated},
     branch::alt,
-    combinator::recognize,
+    combinator::{recognize, value},
     multi::many1,
     error::{Error,
             ErrorKind},
@@ -17,6 +17,7 @@ use nom::character::complete::none_of;
 
 use base_context::simple_context::SimpleContext;
 use base_context::context_traits::Var;
+use serde::{Serialize, Deserialize};
 pub use Triplet::*;
 
 #[derive(PartialEq, Debug, Clone, Copy)]
@@ -25,7 +26,7 @@ pub enum Action {
     Infer
 }
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum CommandType {
     Add,
     Delete,
@@ -48,10 +49,11 @@ pub enum Command {
     Predicat(String, Box<PredicatAST>)
 }
 
-type Premice = String;
-type Conclusion = String;
+type RuleName = Option<String>;
+type Premice = (CommandType, Vec<Triplet>);
+type Conclusion = Box<PredicatAST>;
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub enum PredicatAST {
     Query(
         (Vec<Var>,
@@ -59,8 +61,15 @@ pub enum PredicatAST {
          Vec<Comp>)),
     AddModifier(Vec<Triplet>),
     DeleteModifier(Vec<Triplet>),
-    Infer((CommandType, Vec<Triplet>), Premice, Conclusion),
-    // TODO: add Block and Assert rules
+    Infer(RuleName, Premice, Conclusion),
+    Rules,
+    DropRule(String),
+    EnableRule(String),
+    DisableRule(String),
+    ExplainPlan(Box<PredicatAST>),
+    // a query which must give back at least a row
+    Assert(Box<PredicatAST>),
+    // TODO: add Block rules
     Empty,
     Debug(String)
 }
@@ -77,17 +86,49 @@ impl PredicatAST {
     }
 }
 
+fn join_triplets(v: &[Triplet]) -> String {
+    v.iter().filter(|x| **x != Triplet::Empty)
+        .cloned().map(String::from)
+        .collect::<Vec<_>>().join(" and ")
+}
+
+fn format_query(vars: &[Var], triplets: &[Triplet], comps: &[Comp]) -> String {
+    let conditions = [join_triplets(triplets),
+                      comps.iter().map(|Comp(c)| c.trim().to_string()).collect::<Vec<_>>().join(" and ")]
+        .into_iter().filter(|x| !x.is_empty())
+        .collect::<Vec<_>>().join(" and ");
+    match vars.is_empty() {
+        true => format!("get {}", conditions),
+        false => format!("get {} where {}",
+                         vars.iter().map(|Var(v)| to_var(v)).collect::<Vec<_>>().join(" "),
+                         conditions)
+    }
+}
+
+// gives back a command that the parser understands
 impl From<PredicatAST> for String {
     fn from(p: PredicatAST) -> String {
         match p {
-            PredicatAST::AddModifier(v) => format!("add {}", v.iter().cloned().map(String::from).fold("".to_string(), |acc, x| format!("{} and {}", acc, x))),
-            PredicatAST::DeleteModifier(v) => format!("add {}", v.iter().cloned().map(String::from).fold("".to_string(), |acc, x| format!("{} and {}", acc, x))),
+            PredicatAST::Query((vars, triplets, comps)) => format_query(&vars, &triplets, &comps),
+            PredicatAST::AddModifier(v) => format!("add {}", join_triplets(&v)),
+            PredicatAST::DeleteModifier(v) => format!("delete {}", join_triplets(&v)),
+            PredicatAST::Infer(name, (modifier, triggers), conclusion) => format!("infer {}{} {} -> {}",
+                    name.map(|x| format!("{}: ", x)).unwrap_or_default(),
+                    modifier.get_string(),
+                    join_triplets(&triggers),
+                    String::from(*conclusion)),
+            PredicatAST::Rules => "rules".to_string(),
+            PredicatAST::DropRule(key) => format!("drop rule {}", key),
+            PredicatAST::EnableRule(key) => format!("enable rule {}", key),
+            PredicatAST::DisableRule(key) => format!("disable rule {}", key),
+            PredicatAST::ExplainPlan(query) => format!("explain plan {}", String::from(*query)),
+            PredicatAST::Assert(query) => format!("assert {}", String::from(*query)),
             _ => "".to_string()
         }
     }    
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
 pub struct Comp(pub String);
 
 impl Comp {
@@ -149,7 +190,7 @@ pub fn extract_triplet(tri: &Language) -> Option<Triplet> {
     }
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Triplet {
     Teee(String, String, String),
     Tvee(String, String, String),
@@ -205,10 +246,22 @@ impl From<Triplet> for (String, String, String) {
     }
 }
 
+fn format_element(s: &str) -> String {
+    match s.starts_with('$') || (!s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')) {
+        true => s.to_string(),
+        false => format!("'{}'", s.replace('\'', "''"))
+    }
+}
+
 impl From<Triplet> for String {
     fn from(t: Triplet) -> String {
-        let tri : (String, String, String) = t.into();
-        format!("{} {} {}", tri.0, tri.1, tri.2)
+        let negated = t != t.clone().invert();
+        let (s, l, g) = t.to_tuple_with_variable();
+        format!("{} {}{} {}",
+                format_element(&s),
+                if negated { "not " } else { "" },
+                format_element(&l),
+                format_element(&g))
     }
 }
 
@@ -246,6 +299,21 @@ impl Triplet {
         }
     }
 
+    // inverse of to_tuple_with_variable for the positive triplets
+    pub fn from_tuple_with_variable((a, b, c): (&str, &str, &str)) -> Triplet {
+        let var = |x: &str| x.strip_prefix('$').map(|x| x.to_string());
+        match (var(a), var(b), var(c)) {
+            (None, None, None) => Teee(a.to_string(), b.to_string(), c.to_string()),
+            (Some(a), None, None) => Tvee(a, b.to_string(), c.to_string()),
+            (None, Some(b), None) => Teve(a.to_string(), b, c.to_string()),
+            (None, None, Some(c)) => Teev(a.to_string(), b.to_string(), c),
+            (Some(a), Some(b), None) => Tvve(a, b, c.to_string()),
+            (Some(a), None, Some(c)) => Tvev(a, b.to_string(), c),
+            (None, Some(b), Some(c)) => Tevv(a.to_string(), b, c),
+            (Some(a), Some(b), Some(c)) => Tvvv(a, b, c)
+        }
+    }
+
     pub fn to_tuple(&self) -> (String, String, String) {
         match self {
             Teee(a,b,c) => format_tuple_of_three((a,b,c)),
@@ -338,7 +406,7 @@ pub fn parse_variable(s: &str) -> IResult<&str,Language> {
     alt((parse_not_variable, parse_pure_variable))(s)
 }
 
-fn alpha_num_underscore(s: &str) -> IResult<&str, String> {
+pub fn alpha_num_underscore(s: &str) -> IResult<&str, String> {
     let res = many1(one_of("abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789_"))(s);
     match res {
         Ok((s, v)) => Ok((s, v.iter().collect())),
@@ -354,8 +422,9 @@ fn parse_term(input: &str) -> IResult<&str, Element> {
     }
 }
 
+// a quote inside a string is doubled: 'l''homme'
 fn string_content(s: &str) -> IResult<&str, String> {
-    let res = many1(none_of("'"))(s);
+    let res = many1(alt((none_of("'"), value('\'', tag("''")))))(s);
     match res {
         Ok((s, v)) => Ok((s, v.iter().collect())),
         Err(r) => Err(r)
@@ -2,6 +2,7 @@
 
 mod parse_modifier;
 mod parse_query;
+mod parse_rule;
 pub mod base_parser;
 
 use regex::Regex;
@@ -18,11 +19,15 @@ use parse_query::{
 use nom::combinator::peek;
 use nom::multi::many1;
 use nom::sequence::terminated;
+use nom::sequence::preceded;
 use nom::bytes::complete::tag;
 use nom::sequence::tuple;
 use nom::IResult;
 use nom::combinator::recognize;
 use parse_modifier::parse_modifier;
+use parse_rule::parse_rule_command;
+use base_parser::alpha_num_underscore;
+use nom::combinator::opt;
 use crate::Triplet::*;
 pub use self::base_parser::{Language, Triplet, parse_bar};
 use base_context::context_traits::Var;
@@ -94,15 +99,38 @@ fn parse_cmd(s: &str) -> IResult<&str, (String, Box<PredicatAST>)> {
     }
 }
 
+fn parse_rule_name(s: &str) -> IResult<&str, String> {
+    terminated(alpha_num_underscore, tag(": "))(s)
+}
+
 fn parse_infer(s: &str) -> IResult<&str, PredicatAST> {
     let res = tuple((
             tag("infer "),
+            opt(parse_rule_name),
             parse_trigger,
             tag(" -> "),
             parse_cmd
           ))(s);
     match res {
-        Ok((s, (r, (ty, tri, premi), _, (st, ast)))) => Ok((s, PredicatAST::Infer((ty, tri), st, premi.to_string()))),
+        Ok((s, (r, name, (ty, tri, premi), _, (st, ast)))) => Ok((s, PredicatAST::Infer(name, (ty, tri), ast))),
+        Err(r) => Err(r)
+    }
+}
+
+// explain plan [query]
+fn parse_explain_plan(s: &str) -> IResult<&str, PredicatAST> {
+    let res = preceded(tag("explain plan "), parse_query)(s);
+    match res {
+        Ok((s, query)) => Ok((s, PredicatAST::ExplainPlan(Box::new(query)))),
+        Err(r) => Err(r)
+    }
+}
+
+// assert [query]
+fn parse_assert(s: &str) -> IResult<&str, PredicatAST> {
+    let res = preceded(tag("assert "), parse_query)(s);
+    match res {
+        Ok((s, query)) => Ok((s, PredicatAST::Assert(Box::new(query)))),
         Err(r) => Err(r)
     }
 }
@@ -112,7 +140,10 @@ pub fn parse_command<'a>(s: &'a str) -> Vec<PredicatAST> {
         alt((
             parse_query_and_modifier_bar,
             parse_query_and_modifier,
-            parse_infer
+            parse_infer,
+            parse_explain_plan,
+            parse_assert,
+            parse_rule_command
             // TODO: add validation rule
             ))
         )(s);
@@ -123,6 +154,25 @@ pub fn parse_command<'a>(s: &'a str) -> Vec<PredicatAST> {
     }
 }
 
+// like parse_command, a command which isn't understood to its end being an error
+pub fn try_parse(s: &str) -> Result<Vec<PredicatAST>, String> {
+    let res = many1(
+        alt((
+            parse_query_and_modifier_bar,
+            parse_query_and_modifier,
+            parse_infer,
+            parse_explain_plan,
+            parse_assert,
+            parse_rule_command
+            ))
+        )(s.trim());
+    match res {
+        Ok(("", v)) => Ok(v),
+        Ok((rest, _)) => Err(format!("'{}' can't be parsed from '{}'", s.trim(), rest)),
+        Err(_) => Err(format!("'{}' isn't a predicat command", s.trim()))
+    }
+}
+
 fn is_a_query(s: &str) -> bool {
     s.len() > 3 && &s[0..3] == "get"
 }
@@ -203,6 +253,55 @@ mod tests {
                   )]);
     }
 
+    #[test]
+    fn test_parse_infer_with_name() {
+        assert_eq!(
+            parse_command("infer friendship: add $A ami $B -> add $B ami $A"),
+            vec![PredicatAST::Infer(
+                Some("friendship".to_string()),
+                (CommandType::Add, vec![Triplet::Tvev("A".to_string(), "ami".to_string(), "B".to_string())]),
+                Box::new(PredicatAST::AddModifier(vec![Triplet::Tvev("B".to_string(), "ami".to_string(), "A".to_string())])))]);
+    }
+
+    #[test]
+    fn test_parse_infer_without_name() {
+        assert_eq!(
+            parse_command("infer add $A ami $B -> add $B ami $A"),
+            vec![PredicatAST::Infer(
+                None,
+                (CommandType::Add, vec![Triplet::Tvev("A".to_string(), "ami".to_string(), "B".to_string())]),
+                Box::new(PredicatAST::AddModifier(vec![Triplet::Tvev("B".to_string(), "ami".to_string(), "A".to_string())])))]);
+    }
+
+    #[test]
+    fn test_command_round_trip() {
+        [
+            "add pierre ami julie and julie ami pierre",
+            "delete 'jean claude' ami julie",
+            "add 'l''homme' ami julie",
+            "get $A where $A ami julie",
+            "get $A $B where $A $B julie and $A > 4",
+            "get $A where $A not ami julie",
+            "infer friendship: add $A ami $B -> add $B ami $A",
+            "drop rule friendship",
+            "explain plan get $A where $A ami $B and $B age 24",
+            "assert get $A where $A ami julie",
+        ].iter().for_each(|cmd| {
+            let ast = parse_command(cmd);
+            assert_eq!(ast.len(), 1);
+            assert_eq!(String::from(ast[0].clone()), cmd.to_string());
+            assert_eq!(parse_command(&String::from(ast[0].clone())), ast);
+        });
+    }
+
+    #[test]
+    fn test_try_parse() {
+        assert_eq!(try_parse(" add pierre ami julie "), Ok(parse_command("add pierre ami julie")));
+        assert!(try_parse("get $A").is_err());
+        assert!(try_parse("add pierre ami julie ").is_ok());
+        assert!(try_parse("assert add pierre ami julie").is_err());
+    }
+
     #[test]
     fn test_extract_variable() {
         assert_eq!(
@@ -47,7 +47,7 @@ fn recognize_variable(s: &str) -> IResult<&str,&str> {
 fn parse_string(s: &str) -> IResult<&str,&str> {
    recognize(preceded(space1,delimited(
        char('\''),
-       is_not("\'"),
+       many1(alt((is_not("\'"), tag("''")))),
        char('\''))))(s)
 }
 
@@ -316,6 +316,10 @@ mod tests {
             parse_string(" 'sdt'").unwrap().1,
             " 'sdt'".to_string()
             );
+        assert_eq!(
+            parse_string(" 'l''homme'").unwrap().1,
+            " 'l''homme'".to_string()
+            );
     }
 
     #[test]
@@ -0,0 +1,92 @@
+pub use nom::{
+    bytes::complete::tag,
+    sequence::preceded,
+    branch::alt,
+    combinator::recognize,
+    character::complete::digit1,
+    IResult,
+};
+
+use super::base_parser::{PredicatAST, alpha_num_underscore};
+
+fn parse_rule_key(s: &str) -> IResult<&str, String> {
+    preceded(tag(" rule "), alpha_num_underscore)(s)
+}
+
+fn parse_list_rules(s: &str) -> IResult<&str, PredicatAST> {
+    let res = tag("rules")(s);
+    match res {
+        Ok((t, _)) => Ok((t, PredicatAST::Rules)),
+        Err(e) => Err(e)
+    }
+}
+
+fn parse_drop_rule(s: &str) -> IResult<&str, PredicatAST> {
+    let res = preceded(tag("drop"), parse_rule_key)(s);
+    match res {
+        Ok((t, key)) => Ok((t, PredicatAST::DropRule(key))),
+        Err(e) => Err(e)
+    }
+}
+
+fn parse_enable_rule(s: &str) -> IResult<&str, PredicatAST> {
+    let res = preceded(tag("enable"), parse_rule_key)(s);
+    match res {
+        Ok((t, key)) => Ok((t, PredicatAST::EnableRule(key))),
+        Err(e) => Err(e)
+    }
+}
+
+fn parse_disable_rule(s: &str) -> IResult<&str, PredicatAST> {
+    let res = preceded(tag("disable"), parse_rule_key)(s);
+    match res {
+        Ok((t, key)) => Ok((t, PredicatAST::DisableRule(key))),
+        Err(e) => Err(e)
+    }
+}
+
+// rules | drop rule [name|id] | enable rule [name|id] | disable rule [name|id]
+pub fn parse_rule_command(s: &str) -> IResult<&str, PredicatAST> {
+    alt((
+        parse_list_rules,
+        parse_drop_rule,
+        parse_enable_rule,
+        parse_disable_rule
+        ))(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        parse_rule_command,
+        PredicatAST
+    };
+
+    #[test]
+    fn test_list_rules() {
+        assert_eq!(
+            parse_rule_command("rules").unwrap().1,
+            PredicatAST::Rules);
+    }
+
+    #[test]
+    fn test_drop_rule() {
+        assert_eq!(
+            parse_rule_command("drop rule friendship").unwrap().1,
+            PredicatAST::DropRule("friendship".to_string()));
+        assert_eq!(
+            parse_rule_command("drop rule 3").unwrap().1,
+            PredicatAST::DropRule("3".to_string()));
+    }
+
+    #[test]
+    fn test_enable_disable_rule() {
+        assert_eq!(
+            parse_rule_command("enable rule friendship").unwrap().1,
+            PredicatAST::EnableRule("friendship".to_string()));
+        assert_eq!(
+            parse_rule_command("disable rule friendship").unwrap().1,
+            PredicatAST::DisableRule("friendship".to_string()));
+    }
+
+}
@@ -1,6 +1,10 @@
 #![allow(dead_code, unused_variables, unused_imports, unreachable_code)]
+mod server;
+mod test_runner;
+
 use std::fs;
 use std::env;
+use std::io::{BufRead, BufReader, Write};
 use rustyline::Context as ConsoleContext;
 use rustyline::{Editor, Config, EditMode};
 use rustyline::error::ReadlineError;
@@ -8,16 +12,29 @@ use rustyline::config::CompletionType;
 use rustyline::history::DefaultHistory;
 use rustyline::history::FileHistory;
 use parser::ContextCMD;
-use parser::parse_command;
+use parser::{parse_command, try_parse};
 use knowledge::Cache;
 use knowledge::Knowledgeable;
-use knowledge::SqliteKnowledge;
-use clap::{Command, Arg, ArgMatches};
+use knowledge::{new_knowledge, backend_names, Fact};
+use knowledge::rules_to_dataframe;
+use importer::rdf::{self, RdfFormat};
+use importer::csv::{self, CsvOptions, Header};
+use importer::json::{self, JsonOptions};
+use importer::external::{self, Statement};
+use importer::filesystem::{self, WalkOptions};
+use importer::cargo;
+use importer::git::{self, GitOptions};
+use std::path::Path;
+use std::time::Duration;
+use clap::{Command, Arg, ArgAction, ArgMatches};
+use clap::builder::PossibleValuesParser;
 use parser::base_parser::PredicatAST;
 use base_context::context_traits::Context;
 use metaprogramming::substitute_variables;
 use base_context::simple_context::SimpleContext;
 use base_context::simple_context::DataFrame;
+use base_context::output::{OutputFormat, output_format_names, write_stream};
+use base_context::graph::{GraphFormat, GraphOptions};
 
 struct Cmd(String);
 
@@ -25,17 +42,46 @@ fn to_command(s: &str) -> Cmd {
     Cmd(String::from(s))
 }
 
-struct Interpreter<K: Knowledgeable<DataFrame>> {
+struct Interpreter {
     context: SimpleContext,
-    knowledge: K
+    knowledge: Box<dyn Knowledgeable<DataFrame>>,
+    format: OutputFormat,
+    // the results aren't printed, only the errors
+    quiet: bool
+}
+
+// why a command failed, each reason having its exit code
+#[derive(Debug, Clone, PartialEq)]
+enum Failure {
+    Command(String),
+    Syntax(String),
+    Assertion(String)
 }
 
-impl<K: Knowledgeable<DataFrame>> Interpreter<K> {
+impl Failure {
+    fn code(&self) -> i32 {
+        match self {
+            Failure::Command(_) => 1,
+            Failure::Syntax(_) => 2,
+            Failure::Assertion(_) => 3
+        }
+    }
 
-    fn new(k: K) -> Self {
+    fn message(&self) -> &str {
+        match self {
+            Failure::Command(message) | Failure::Syntax(message) | Failure::Assertion(message) => message
+        }
+    }
+}
+
+impl Interpreter {
+
+    fn new(k: Box<dyn Knowledgeable<DataFrame>>) -> Self {
         Interpreter { 
             context: SimpleContext::default(),
-            knowledge: k
+            knowledge: k,
+            format: OutputFormat::default(),
+            quiet: false
             }
     }
 
@@ -51,11 +97,13 @@ impl<K: Knowledgeable<DataFrame>> Interpreter<K> {
     }
 
     fn run(&mut self, cmd: &str) -> SimpleContext {
-        Some(&vec![cmd.to_string()])
-            .map(|x| self.parse(x))
-            .map(|x| self.execute(&x).unwrap_or_default())
-            .map(|x| self.propagate(x))
-            .unwrap()
+        let cmds = self.parse(&[cmd.to_string()]);
+        self.run_commands(&cmds)
+    }
+
+    fn run_commands(&mut self, cmds: &[PredicatAST]) -> SimpleContext {
+        let context = self.execute(cmds).unwrap_or_default();
+        self.propagate(context)
     }
 
     fn clear_cache(&self) -> () {
@@ -63,12 +111,64 @@ impl<K: Knowledgeable<DataFrame>> Interpreter<K> {
     }
 
     fn display(&self) -> () {
-        self.context.display()
+        self.context.display_as(self.format)
+    }
+
+    // a lone query is printed row by row, any other command once it has run
+    fn run_and_display(&mut self, line: &str) -> Result<(), Failure> {
+        let commands = Self::substitute(try_parse(line).map_err(Failure::Syntax)?);
+        match &commands[..] {
+            [query @ PredicatAST::Query(_)] => {
+                let mut out: Box<dyn Write> = match self.quiet {
+                    true => Box::new(std::io::sink()),
+                    false => Box::new(std::io::stdout().lock())
+                };
+                self.knowledge.valid_commands(vec![query.clone()])
+                    .and_then(|(_, warnings)| {
+                        warnings.iter().for_each(|warning| eprintln!("warning: {}", warning));
+                        self.knowledge.stream(query)
+                    })
+                    .and_then(|rows| write_stream(rows, self.format, &mut out))
+                    .map(|_| ())
+                    .map_err(Failure::Command)
+            },
+            _ => {
+                let context = self.run_commands(&commands);
+                match (context.has_error(), &commands[..]) {
+                    (true, [PredicatAST::Assert(_)]) => Err(Failure::Assertion(context.log.join(", "))),
+                    (true, _) => Err(Failure::Command(context.log.join(", "))),
+                    (false, [PredicatAST::Assert(_)]) => Ok(()),
+                    (false, _) if self.quiet => Ok(()),
+                    (false, _) => { self.display(); Ok(()) }
+                }
+            }
+        }
+    }
+
+    // an assertion gives back nothing when it holds, an error otherwise
+    fn assert(&self, query: &PredicatAST) -> SimpleContext {
+        match self.knowledge.execute_command(query).map(|df| df.len()) {
+            Ok(0) => SimpleContext {
+                log: vec![format!("assertion failed: {}", String::from(query.clone()))],
+                ..SimpleContext::default()
+            },
+            Ok(_) => SimpleContext::default(),
+            Err(error) => SimpleContext { log: vec![error], ..SimpleContext::default() }
+        }
+    }
+
+    fn show(&self, df: DataFrame) {
+        if !self.quiet {
+            SimpleContext::from(df).display_as(self.format)
+        }
     }
 
     fn single_parse(command: &String) -> Vec<PredicatAST> {
-        parse_command(command).iter()
-                    .map(PredicatAST::clone)
+        Self::substitute(parse_command(command))
+    }
+
+    fn substitute(commands: Vec<PredicatAST>) -> Vec<PredicatAST> {
+        commands.into_iter()
                     .flat_map(substitute_variables(SimpleContext::new()))
                     .flatten().collect()
     }
@@ -89,12 +189,22 @@ impl<K: Knowledgeable<DataFrame>> Interpreter<K> {
     }
 
     fn execute(&self, cmds: &[PredicatAST]) -> Option<SimpleContext> {
-        let context = self.knowledge
-                .valid_commands(cmds.to_vec())?.iter()
+        let (cmds, warnings) = match self.knowledge.valid_commands(cmds.to_vec()) {
+            Ok(valid) => valid,
+            Err(error) => return Some(SimpleContext { log: vec![error], ..SimpleContext::default() })
+        };
+        warnings.iter().for_each(|warning| eprintln!("warning: {}", warning));
+        let context = cmds.iter()
                 .filter(|cmd| !self.knowledge.in_cache(cmd))
                 .map(|cmd| (cmd, self.knowledge.infer_commands_from(cmd)))
                 .map(|(cmd, aftcmd)| {
-                        let context: SimpleContext = self.knowledge.execute_command(cmd).into();
+                        let context: SimpleContext = match cmd {
+                            PredicatAST::Assert(query) => self.assert(query),
+                            _ => match self.knowledge.execute_command(cmd) {
+                                Ok(df) => df.into(),
+                                Err(error) => SimpleContext { log: vec![error], ..SimpleContext::default() }
+                            }
+                        };
                             context.add_aftercmd(&aftcmd)
                 })
                 .reduce(SimpleContext::join_contexts)?;
@@ -108,40 +218,530 @@ impl<K: Knowledgeable<DataFrame>> Interpreter<K> {
     fn clear(&self) -> () {
         self.knowledge.clear_all();
     }
-    
+
+    fn load(&mut self, file_name: &str) -> Result<(), String> {
+        let content = fs::read_to_string(file_name)
+            .map_err(|_| format!("le fichier '{}' est illisible", file_name))?;
+        process_string(&content).iter().for_each(|cmd| {self.run(cmd);});
+        Ok(())
+    }
+
+    // a fact by line, the malformed lines are skipped, a read error stops the load
+    fn bulk_load(&self, file_name: &str, infer: bool) -> Result<(usize, usize), String> {
+        let file = fs::File::open(file_name)
+            .map_err(|_| format!("le fichier '{}' est illisible", file_name))?;
+        let mut error = None;
+        let mut facts = BufReader::new(file).lines()
+            .map_while(|line| line.map_err(|e| error = Some(e)).ok())
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .filter_map(|(number, line)| parse_fact(&line).or_else(|| {
+                eprintln!("warning: line {} isn't a fact and is skipped", number + 1);
+                None
+            }));
+        let loaded = self.insert_facts(&mut facts, infer);
+        drop(facts);
+        match error {
+            Some(e) => Err(format!("le fichier '{}' est illisible ({})", file_name, e)),
+            None => loaded
+        }
+    }
+
+    fn insert_facts(&self, facts: &mut dyn Iterator<Item = Fact>, infer: bool) -> Result<(usize, usize), String> {
+        let mut reported = false;
+        let mut progress = |count: usize| if count.is_multiple_of(PROGRESS_STEP) {
+            eprint!("\r{} facts read", count);
+            let _ = std::io::stderr().flush();
+            reported = true;
+        };
+        let loaded = self.knowledge.bulk_load(facts, &mut progress, infer);
+        if reported {
+            eprintln!();
+        }
+        loaded
+    }
+
+    fn import(&self, file_name: &str, format: RdfFormat, infer: bool) -> Result<(usize, usize), String> {
+        let content = fs::read_to_string(file_name)
+            .map_err(|_| format!("le fichier '{}' est illisible", file_name))?;
+        let triples = rdf::read(format, &content)?;
+        self.insert_facts(&mut triples.into_iter(), infer)
+    }
+
+    fn import_csv(&self, file_name: &str, template: &str, options: &CsvOptions, infer: bool) -> Result<(usize, usize), String> {
+        let content = fs::read_to_string(file_name)
+            .map_err(|_| format!("le fichier '{}' est illisible", file_name))?;
+        let triples = csv::read(&content, template, options)?;
+        self.insert_facts(&mut triples.into_iter(), infer)
+    }
+
+    fn import_json(&self, file_name: &str, options: &JsonOptions, infer: bool) -> Result<(usize, usize), String> {
+        let content = fs::read_to_string(file_name)
+            .map_err(|_| format!("le fichier '{}' est illisible", file_name))?;
+        let triples = json::read(&content, options)?;
+        self.insert_facts(&mut triples.into_iter(), infer)
+    }
+
+    fn import_tree(&self, root: &str, options: &WalkOptions, infer: bool) -> Result<(usize, usize), String> {
+        let triples = filesystem::read(Path::new(root), options, &mut |e| eprintln!("warning: {}, the entry is skipped", e))?;
+        self.insert_facts(&mut triples.into_iter(), infer)
+    }
+
+    fn import_workspace(&self, root: &str, infer: bool) -> Result<(usize, usize), String> {
+        let triples = cargo::read(Path::new(root))?;
+        self.insert_facts(&mut triples.into_iter(), infer)
+    }
+
+    fn import_history(&self, repository: &str, options: &GitOptions, infer: bool) -> Result<(usize, usize), String> {
+        let triples = git::read(Path::new(repository), options)?;
+        self.insert_facts(&mut triples.into_iter(), infer)
+    }
+
+    // the facts are stored while the importer runs, its other commands once it is done
+    fn import_external(&mut self, program: &str, args: &[String], timeout: Option<Duration>, infer: bool) -> Result<(usize, usize), String> {
+        let mut import = external::spawn(program, args, timeout)?;
+        let mut commands = vec![];
+        let mut facts = import.by_ref()
+            .filter_map(|statement| match statement {
+                Ok(Statement::Facts(facts)) => Some(facts),
+                Ok(Statement::Command(command)) => { commands.push(command); None },
+                Err(e) => { eprintln!("warning: {}, the line is skipped", e); None }
+            })
+            .flatten();
+        let loaded = self.insert_facts(&mut facts, infer)?;
+        import.finish()?;
+        commands.iter()
+            .map(|command| self.run(command))
+            .filter(SimpleContext::has_error)
+            .for_each(|context| context.display());
+        Ok(loaded)
+    }
+
+    fn export(&self, format: RdfFormat) -> String {
+        let facts = self.knowledge.get_all()
+            .get_values2(&["$A", "$B", "$C"])
+            .unwrap_or_default()
+            .into_iter()
+            .map(|row| (row[0].clone(), row[1].clone(), row[2].clone()))
+            .collect::<Vec<_>>();
+        rdf::write(format, &facts)
+    }
+
+    // the facts, or the result of a query over a subject, a link and a goal, as a graph
+    fn graph(&mut self, query: Option<&str>, format: GraphFormat, options: &GraphOptions) -> Result<String, String> {
+        let df = match query {
+            Some(query) => {
+                let context = self.run(query);
+                match context.has_error() {
+                    true => return Err(context.log.join(", ")),
+                    false => context.get_tab()
+                }
+            },
+            None => self.knowledge.get_all()
+        };
+        df.to_graph(format, options)
+    }
+
+    fn meta_command(&mut self, line: &str) -> Result<(), String> {
+        let (name, arg) = line.trim().split_once(' ')
+            .map(|(name, arg)| (name, arg.trim()))
+            .unwrap_or((line.trim(), ""));
+        match (name, arg) {
+            (".facts", _) => self.show(self.knowledge.get_all()),
+            (".rules", _) => self.show(rules_to_dataframe(&self.knowledge.get_rules())),
+            (".links", _) => self.show(column_to_dataframe("link", &self.knowledge.get_links())),
+            (".stats", _) => self.display_stats(),
+            (".ast", cmd) if !cmd.is_empty() =>
+                Self::single_parse(&cmd.to_string()).iter()
+                    .for_each(|ast| println!("{:#?}", ast)),
+            (".sql", cmd) if !cmd.is_empty() =>
+                Self::single_parse(&cmd.to_string()).iter()
+                    .map(|ast| self.knowledge.explain(ast))
+                    .collect::<Result<Vec<_>, String>>()?
+                    .iter().flatten()
+                    .for_each(|sql| println!("{}", sql)),
+            (".clear", _) => self.clear(),
+            (".load", file) if !file.is_empty() => self.load(file)?,
+            (".format", format) if !format.is_empty() => self.format = OutputFormat::try_from(format)?,
+            (".graph", arg) => {
+                let (format, options, query) = graph_arguments(arg)?;
+                print!("{}", self.graph(query, format, &options)?)
+            },
+            (".help", _) => println!("{}", META_COMMANDS_HELP),
+            (".ast", _) | (".sql", _) | (".load", _) | (".format", _) =>
+                return Err(format!("'{}' needs an argument, see .help", name)),
+            (name, _) => return Err(format!("Unknown command '{}', see .help", name))
+        };
+        Ok(())
+    }
+
+    fn display_stats(&self) {
+        let stats = [
+            ("facts", self.knowledge.count_facts()),
+            ("links", self.knowledge.get_links().len()),
+            ("rules", self.knowledge.get_rules().len())
+        ];
+        let df: DataFrame = stats.iter()
+            .flat_map(|(name, count)| [("name".to_string(), name.to_string()),
+                                       ("count".to_string(), count.to_string())])
+            .collect::<Vec<_>>()
+            .try_into().unwrap_or_default();
+        self.show(df)
+    }
+
+}
+
+static META_COMMANDS_HELP: &str = "\
+.facts          display every fact
+.rules          display every rule
+.links          display the distinct links
+.stats          display the number of facts, links and rules
+.ast <cmd>      display the parsed AST of a command
+.sql <cmd>      display the translation of a command
+.clear          remove every fact, rule and cached command
+.load <file>    execute the commands of a file
+.format <name>  display the results as a table, json, csv, tsv or markdown
+.graph [dot|mermaid] [--link <link>]... [--max-nodes <n>] [query]
+                display the facts, or the result of a query over $A $B $C, as a graph
+.help           display this help
+exit            quit the shell";
+
+static EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0  every command ran
+  1  a command failed or was rejected, like a rule conflicting with another one
+  2  a command can't be parsed
+  3  an assertion (assert <query>) gave back no row";
+
+static PROGRESS_STEP: usize = 10_000;
+static RDF_FORMATS: [&str; 2] = ["nt", "ttl"];
+static EXPORT_FORMATS: [&str; 4] = ["nt", "ttl", "dot", "mermaid"];
+static IMPORT_FORMATS: [&str; 9] = ["nt", "ttl", "csv", "tsv", "json", "jsonl", "dir", "cargo", "git"];
+
+// the arguments of .graph: a format, then the options, the rest being the query
+fn graph_arguments(arg: &str) -> Result<(GraphFormat, GraphOptions, Option<&str>), String> {
+    let mut format = GraphFormat::default();
+    let mut options = GraphOptions::default();
+    let mut rest = arg.trim();
+    loop {
+        let (word, tail) = rest.split_once(char::is_whitespace)
+            .map(|(word, tail)| (word, tail.trim_start()))
+            .unwrap_or((rest, ""));
+        match word {
+            "dot" | "mermaid" => format = GraphFormat::try_from(word)?,
+            "--link" | "--max-nodes" => {
+                let (value, tail) = tail.split_once(char::is_whitespace)
+                    .map(|(value, tail)| (value, tail.trim_start()))
+                    .unwrap_or((tail, ""));
+                match (word, value) {
+                    (_, "") => return Err(format!("'{}' needs a value", word)),
+                    ("--link", link) => options.links.push(link.to_string()),
+                    (_, max) => options.max_nodes = Some(max.parse()
+                        .map_err(|_| format!("'{}' isn't a number of nodes", max))?)
+                }
+                rest = tail;
+                continue;
+            },
+            _ => break
+        }
+        rest = tail;
+    }
+    Ok((format, options, Some(rest).filter(|query| !query.is_empty())))
+}
+
+// subject, link and goal separated by tabulations, or by spaces when none of them has one
+fn parse_fact(line: &str) -> Option<Fact> {
+    let fields = match line.contains('\t') {
+        true => line.split('\t').map(str::trim).collect::<Vec<_>>(),
+        false => line.split_whitespace().collect()
+    };
+    match fields[..] {
+        [subject, link, goal] if !subject.is_empty() && !link.is_empty() && !goal.is_empty() =>
+            Some((subject.to_string(), link.to_string(), goal.to_string())),
+        _ => None
+    }
 }
 
+fn column_to_dataframe(name: &str, values: &[String]) -> DataFrame {
+    values.iter()
+        .map(|x| (name.to_string(), x.clone()))
+        .collect::<Vec<_>>()
+        .try_into().unwrap_or_default()
+}
+
+
+fn output_format_arg() -> Arg {
+    Arg::new("format")
+        .long("format")
+        .help("The format of the results")
+        .value_parser(PossibleValuesParser::new(output_format_names()))
+        .default_value(output_format_names()[0])
+}
+
+fn quiet_arg() -> Arg {
+    Arg::new("quiet")
+        .long("quiet")
+        .short('q')
+        .action(ArgAction::SetTrue)
+        .help("Print the errors only, not the results")
+}
 
-fn open(file_name: &str) -> String {
-    fs::read_to_string(file_name)
-                .expect(&format!("le fichier '{}' est illisible", file_name))
+fn output_format(sub_matches: &ArgMatches) -> OutputFormat {
+    sub_matches.get_one::<String>("format")
+        .and_then(|format| OutputFormat::try_from(&format[..]).ok())
+        .unwrap_or_default()
 }
 
 fn get_user_input() -> ArgMatches {
     Command::new("MyApp")
         .subcommand_required(true)
         .arg_required_else_help(true)
+        .arg(
+            Arg::new("backend")
+                .long("backend")
+                .global(true)
+                .help("The store holding the facts and the rules")
+                .value_parser(PossibleValuesParser::new(backend_names()))
+                .default_value(backend_names()[0])
+        )
         .subcommand(
             Command::new("cmd")
                 .about("Run a command passed as a parameter")
-                .arg(Arg::new("name"))
+                .arg(Arg::new("name").required(true))
+                .arg(output_format_arg())
+                .arg(quiet_arg())
+                .after_help(EXIT_CODES_HELP)
         )
         .subcommand(
             Command::new("open")
                 .about("Open a file and execute its predicat's comment")
-                .arg(Arg::new("name"))
+                .arg(Arg::new("name").required(true))
+                .arg(output_format_arg())
+                   )
+        .subcommand(
+            Command::new("run")
+                .about("Run the commands of a file, a line for each, stopping at the first one which fails")
+                .arg(Arg::new("file").required(true).help("The file of the commands, '-' for the standard input"))
+                .arg(output_format_arg())
+                .arg(quiet_arg())
+                .after_help(EXIT_CODES_HELP)
+                   )
+        .subcommand(
+            Command::new("load")
+                .about("Insert the facts of a file, one 'subject link goal' by line")
+                .arg(Arg::new("file").required(true))
+                .arg(
+                    Arg::new("no-inference")
+                        .long("no-inference")
+                        .action(ArgAction::SetTrue)
+                        .help("Don't run the rules on the loaded facts")
+                )
+                   )
+        .subcommand(
+            Command::new("import")
+                .about("Insert the facts of an N-Triples, Turtle, CSV, JSON or JSON Lines file, of a directory tree, of a Cargo workspace or of an importer")
+                .arg(Arg::new("file").required_unless_present("importer"))
+                .arg(
+                    Arg::new("importer")
+                        .long("importer")
+                        .num_args(1..)
+                        .allow_hyphen_values(true)
+                        .value_names(["binary", "args"])
+                        .conflicts_with("file")
+                        .help("A program writing N-Triples or predicat commands on its output, a line for each, \
+                               the last option as it takes every argument after it")
+                )
+                .arg(
+                    Arg::new("timeout")
+                        .long("timeout")
+                        .requires("importer")
+                        .value_parser(clap::value_parser!(u64))
+                        .help("The seconds after which the importer is stopped")
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .help("The format of the file, guessed from its extension by default, \
+                               cargo reading the Cargo.toml files and the Cargo.lock of a workspace, \
+                               git the history of a repository")
+                        .value_parser(PossibleValuesParser::new(IMPORT_FORMATS))
+                )
+                .arg(
+                    Arg::new("template")
+                        .long("template")
+                        .help("The facts of a CSV row, like '$name works_at $company', the columns being the variables")
+                )
+                .arg(
+                    Arg::new("delimiter")
+                        .long("delimiter")
+                        .help("The delimiter of the CSV columns, ',' by default or 'tab' for a tsv file")
+                )
+                .arg(
+                    Arg::new("header")
+                        .long("header")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("no-header")
+                        .help("The first CSV row names the columns, detected from the template by default")
+                )
+                .arg(
+                    Arg::new("no-header")
+                        .long("no-header")
+                        .action(ArgAction::SetTrue)
+                        .help("The CSV columns are named $c1, $c2...")
+                )
+                .arg(
+                    Arg::new("include")
+                        .long("include")
+                        .action(ArgAction::Append)
+                        .help("A glob of the files of a directory to import, every file by default")
+                )
+                .arg(
+                    Arg::new("exclude")
+                        .long("exclude")
+                        .action(ArgAction::Append)
+                        .help("A glob of the files or directories to leave out, like 'target' or '*.lock'")
+                )
+                .arg(
+                    Arg::new("revision")
+                        .long("revision")
+                        .help("The commits of a git history to import, like 'main' or 'v1.0..HEAD', HEAD by default")
+                )
+                .arg(
+                    Arg::new("max-count")
+                        .long("max-count")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("The number of most recent commits of a git history to import")
+                )
+                .arg(
+                    Arg::new("id-field")
+                        .long("id-field")
+                        .default_value("id")
+                        .help("The field naming a JSON object, a blank node unique to the import being generated without it")
+                )
+                .arg(
+                    Arg::new("no-inference")
+                        .long("no-inference")
+                        .action(ArgAction::SetTrue)
+                        .help("Don't run the rules on the imported facts")
+                )
+                   )
+        .subcommand(
+            Command::new("export")
+                .about("Write every fact as N-Triples or Turtle, or draw them as a Graphviz or Mermaid graph")
+                .arg(Arg::new("file").help("The file to write, the standard output by default"))
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_parser(PossibleValuesParser::new(EXPORT_FORMATS))
+                        .default_value(EXPORT_FORMATS[0])
+                )
+                .arg(
+                    Arg::new("query")
+                        .long("query")
+                        .help("A query over a subject, a link and a goal, like 'get $A $B $C where $A ami $C', \
+                               drawn instead of every fact")
+                )
+                .arg(
+                    Arg::new("link")
+                        .long("link")
+                        .action(ArgAction::Append)
+                        .help("A link to draw, every one by default")
+                )
+                .arg(
+                    Arg::new("max-nodes")
+                        .long("max-nodes")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("The number of nodes past which the edges aren't drawn")
+                )
                    )
         .subcommand(
             Command::new("shell")
                 .about("Execute an interactive shell for predicat")
+                .arg(output_format_arg())
+                   )
+        .subcommand(
+            Command::new("test")
+                .about("Run the .pred test files, each against a fresh memory knowledge, failing when an expectation isn't met")
+                .arg(Arg::new("path").required(true).num_args(1..).help("A .pred file or a directory holding some"))
+                   )
+        .subcommand(
+            Command::new("serve")
+                .about("Answer the queries of other programs over HTTP with JSON")
+                .arg(
+                    Arg::new("host")
+                        .long("host")
+                        .default_value("127.0.0.1")
+                        .help("The address to listen on")
+                )
+                .arg(
+                    Arg::new("port")
+                        .long("port")
+                        .value_parser(clap::value_parser!(u16))
+                        .default_value("7878")
+                )
                    )
         .get_matches()
 }
 
-fn one_command(val: &String) -> () {
-    let mut interpreter = Interpreter::new(SqliteKnowledge::new());
-    interpreter.run(&val);
-    interpreter.display();
+fn serve(sub_matches: &ArgMatches, backend: &str) {
+    let host = sub_matches.get_one::<String>("host").expect("The host has a default value");
+    let port = sub_matches.get_one::<u16>("port").expect("The port has a default value");
+    if let Err(e) = server::serve(backend, &format!("{}:{}", host, port)) {
+        eprintln!("error: {}", e);
+        std::process::exit(1)
+    }
+}
+
+fn open_interpreter(backend: &str) -> Interpreter {
+    match new_knowledge(backend) {
+        Ok(knowledge) => Interpreter::new(knowledge),
+        Err(e) => { eprintln!("error: {}", e); std::process::exit(1) }
+    }
+}
+
+fn one_command(val: &str, backend: &str, format: OutputFormat, quiet: bool) {
+    let mut interpreter = open_interpreter(backend);
+    interpreter.format = format;
+    interpreter.quiet = quiet;
+    if let Err(failure) = interpreter.run_and_display(val) {
+        eprintln!("error: {}", failure.message());
+        std::process::exit(failure.code())
+    }
+}
+
+// the commands of a script, a line for each, the first failure stopping it
+fn run_script(interpreter: &mut Interpreter, script: impl BufRead) -> Result<(), (usize, Failure)> {
+    for (number, line) in script.lines().enumerate() {
+        let line = line.map_err(|e| (number + 1, Failure::Command(e.to_string())))?;
+        let line = line.trim();
+        if line == "exit" {
+            break;
+        }
+        let ran = match line.chars().next() {
+            None | Some('#') => Ok(()),
+            Some('.') => interpreter.meta_command(line).map_err(Failure::Command),
+            _ => interpreter.run_and_display(line)
+        };
+        ran.map_err(|failure| (number + 1, failure))?;
+    }
+    Ok(())
+}
+
+fn run_file(file_name: &str, backend: &str, format: OutputFormat, quiet: bool) {
+    let mut interpreter = open_interpreter(backend);
+    interpreter.format = format;
+    interpreter.quiet = quiet;
+    let ran = match file_name {
+        "-" => run_script(&mut interpreter, std::io::stdin().lock()),
+        _ => match fs::File::open(file_name) {
+            Ok(file) => run_script(&mut interpreter, BufReader::new(file)),
+            Err(e) => { eprintln!("error: '{}' can't be read ({})", file_name, e); std::process::exit(1) }
+        }
+    };
+    if let Err((number, failure)) = ran {
+        eprintln!("error: line {}: {}", number, failure.message());
+        std::process::exit(failure.code())
+    }
 }
 
 fn process_string(input: &str) -> Vec<String> {
@@ -153,14 +753,119 @@ fn process_string(input: &str) -> Vec<String> {
     vect.pop(); vect
 }
 
-fn read_file(val: &String) -> () {
-    let val = open(val);
-    let lines = process_string(&val);
-    let mut interpreter = Interpreter::new(SqliteKnowledge::new());
-    lines.iter().for_each(|cmd| {interpreter.run(cmd);});
+fn read_file(val: &str, backend: &str, format: OutputFormat) {
+    let mut interpreter = open_interpreter(backend);
+    interpreter.format = format;
+    if let Err(e) = interpreter.load(val) {
+        eprintln!("error: {}", e);
+        std::process::exit(1)
+    }
     interpreter.display();
 }
 
+fn load_facts(file_name: &str, backend: &str, infer: bool) {
+    let interpreter = open_interpreter(backend);
+    match interpreter.bulk_load(file_name, infer) {
+        Ok((read, inferred)) => println!("{} facts read, {} inferred", read, inferred),
+        Err(e) => { eprintln!("error: {}", e); std::process::exit(1) }
+    }
+}
+
+fn csv_options(sub_matches: &ArgMatches, format: &str) -> Result<CsvOptions, String> {
+    let delimiter = match (sub_matches.get_one::<String>("delimiter").map(|x| &x[..]), format) {
+        (Some("tab") | Some("\\t"), _) | (None, "tsv") => b'\t',
+        (None, _) => b',',
+        (Some(delimiter), _) if delimiter.len() == 1 => delimiter.as_bytes()[0],
+        (Some(delimiter), _) => return Err(format!("The delimiter '{}' isn't a single character", delimiter))
+    };
+    let header = match (sub_matches.get_flag("header"), sub_matches.get_flag("no-header")) {
+        (true, _) => Header::Present,
+        (_, true) => Header::Absent,
+        _ => Header::Detect
+    };
+    Ok(CsvOptions { delimiter, header })
+}
+
+fn run_importer(importer: Vec<String>, sub_matches: &ArgMatches, backend: &str) {
+    let mut interpreter = open_interpreter(backend);
+    let timeout = sub_matches.get_one::<u64>("timeout").map(|seconds| Duration::from_secs(*seconds));
+    let imported = interpreter.import_external(&importer[0], &importer[1..], timeout,
+                                               !sub_matches.get_flag("no-inference"));
+    match imported {
+        Ok((read, inferred)) => println!("{} facts read, {} inferred", read, inferred),
+        Err(e) => { eprintln!("error: {}", e); std::process::exit(1) }
+    }
+}
+
+fn import_facts(file_name: &str, sub_matches: &ArgMatches, backend: &str) {
+    let interpreter = open_interpreter(backend);
+    let infer = !sub_matches.get_flag("no-inference");
+    let format = sub_matches.get_one::<String>("format").cloned()
+        .or_else(|| file_name.ends_with("Cargo.toml").then(|| "cargo".to_string()))
+        .or_else(|| Path::new(file_name).is_dir().then(|| "dir".to_string()))
+        .or_else(|| file_name.rsplit_once('.').map(|(_, extension)| extension.to_string())
+                 .filter(|extension| IMPORT_FORMATS.contains(&&extension[..])))
+        .ok_or(format!("The format of '{}' can't be guessed, use --format", file_name));
+    let imported = format.and_then(|format| match &format[..] {
+        "csv" | "tsv" => {
+            let template = sub_matches.get_one::<String>("template")
+                .ok_or("A CSV needs a --template to become facts".to_string())?;
+            interpreter.import_csv(file_name, template, &csv_options(sub_matches, &format)?, infer)
+        },
+        "json" | "jsonl" => {
+            let options = JsonOptions {
+                id_field: sub_matches.get_one::<String>("id-field").expect("The id field has a default").clone(),
+                lines: format == "jsonl",
+                ..JsonOptions::default()
+            };
+            interpreter.import_json(file_name, &options, infer)
+        },
+        "dir" => {
+            let globs = |name: &str| sub_matches.get_many::<String>(name).map(|x| x.cloned().collect()).unwrap_or_default();
+            interpreter.import_tree(file_name, &WalkOptions { include: globs("include"), exclude: globs("exclude") }, infer)
+        },
+        "cargo" => interpreter.import_workspace(file_name, infer),
+        "git" => {
+            let options = GitOptions {
+                revision: sub_matches.get_one::<String>("revision").cloned(),
+                max_count: sub_matches.get_one::<usize>("max-count").copied()
+            };
+            interpreter.import_history(file_name, &options, infer)
+        },
+        rdf => RdfFormat::try_from(rdf).and_then(|format| interpreter.import(file_name, format, infer))
+    });
+    match imported {
+        Ok((read, inferred)) => println!("{} facts read, {} inferred", read, inferred),
+        Err(e) => { eprintln!("error: {}", e); std::process::exit(1) }
+    }
+}
+
+fn export_facts(file_name: Option<&String>, sub_matches: &ArgMatches, backend: &str) {
+    let mut interpreter = open_interpreter(backend);
+    let format = sub_matches.get_one::<String>("format").expect("The format has a default value");
+    let document = match GraphFormat::try_from(&format[..]) {
+        Ok(graph) => {
+            let options = GraphOptions {
+                links: sub_matches.get_many::<String>("link").map(|x| x.cloned().collect()).unwrap_or_default(),
+                max_nodes: sub_matches.get_one::<usize>("max-nodes").copied()
+            };
+            let query = sub_matches.get_one::<String>("query").map(String::as_str);
+            interpreter.graph(query, graph, &options)
+        },
+        Err(_) => RdfFormat::try_from(&format[..]).map(|format| interpreter.export(format))
+    };
+    let written = match (document, file_name) {
+        (Ok(document), Some(file_name)) => fs::write(file_name, document)
+            .map_err(|e| format!("le fichier '{}' ne peut pas être écrit ({})", file_name, e)),
+        (Ok(document), None) => { print!("{}", document); Ok(()) },
+        (Err(e), _) => Err(e)
+    };
+    if let Err(e) = written {
+        eprintln!("error: {}", e);
+        std::process::exit(1)
+    }
+}
+
 fn generate_shell() -> Editor<(), FileHistory> {
     let config = Config::builder()
         .edit_mode(EditMode::Emacs)
@@ -172,32 +877,67 @@ fn generate_shell() -> Editor<(), FileHistory> {
         .expect("Erreur lors de l'initialisation de l'éditeur")
 }
 
-fn shell() {
+fn shell(backend: &str, format: OutputFormat) {
     let mut rl = generate_shell();
+    let mut interpreter = open_interpreter(backend);
+    interpreter.format = format;
     loop {
         let readline = rl.readline(">> ");
         match readline {
             Ok(x) if x == "exit" => break,
-            Ok(x) if &x[0..5] == "parse" => break,
+            Ok(line) if line.trim().starts_with('.') => {
+                let _ = rl.add_history_entry(line.as_str());
+                if let Err(e) = interpreter.meta_command(&line) {
+                    eprintln!("error: {}", e);
+                }},
             Ok(line) => {
                 let _ = rl.add_history_entry(line.as_str());
-                one_command(&line)},
+                if let Err(failure) = interpreter.run_and_display(&line) {
+                    eprintln!("error: {}", failure.message());
+                }},
             Err(ReadlineError::Interrupted) => {
                 println!("CTRL-C");
                 break;
             },
-            Err(_) => println!("No input"),
+            Err(ReadlineError::Eof) => break,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                break;
+            }
         }
     }
 }
 
 fn main() {
-    match get_user_input().subcommand() {
+    let matches = get_user_input();
+    let backend = matches.get_one::<String>("backend").expect("The backend has a default value");
+    match matches.subcommand() {
         Some(("cmd", sub_matches)) => one_command(sub_matches.get_one::<String>("name")
-                                                  .expect("No command where given as an argument")), 
+                                                  .expect("The command is required"), backend,
+                                                  output_format(sub_matches), sub_matches.get_flag("quiet")),
+        Some(("run", sub_matches)) => run_file(sub_matches.get_one::<String>("file")
+                                               .expect("The file is required"), backend,
+                                               output_format(sub_matches), sub_matches.get_flag("quiet")),
         Some(("open", sub_matches)) => read_file(sub_matches.get_one::<String>("name")
-                                                  .expect("No file name where given")), 
-        Some(("shell", sub_matches)) => shell(),
+                                                  .expect("The file name is required"), backend,
+                                                  output_format(sub_matches)),
+        Some(("load", sub_matches)) => load_facts(sub_matches.get_one::<String>("file")
+                                                  .expect("The file is required"), backend,
+                                                  !sub_matches.get_flag("no-inference")),
+        Some(("import", sub_matches)) => match sub_matches.get_many::<String>("importer") {
+            Some(importer) => run_importer(importer.cloned().collect(), sub_matches, backend),
+            None => import_facts(sub_matches.get_one::<String>("file")
+                                 .expect("The file is required without an importer"), sub_matches, backend)
+        },
+        Some(("export", sub_matches)) => export_facts(sub_matches.get_one::<String>("file"), sub_matches, backend),
+        Some(("shell", sub_matches)) => shell(backend, output_format(sub_matches)),
+        Some(("serve", sub_matches)) => serve(sub_matches, backend),
+        Some(("test", sub_matches)) => {
+            let paths = sub_matches.get_many::<String>("path").expect("The path is required").cloned().collect::<Vec<_>>();
+            if !test_runner::run(&paths) {
+                std::process::exit(1)
+            }
+        },
         _ => unreachable!("Exhausted list of subcommands and subcommand_required prevents `None`"),
     }
 }
@@ -206,13 +946,12 @@ fn main() {
 mod tests {
     use super::*;
     use knowledge::base_knowledge::Command;
-    use serial_test::serial;
     use knowledge::RuleManager;
+    use knowledge::FactManager;
 
     #[test]
-    #[serial]
     fn test_add(){
-       let mut interpreter = Interpreter::new(SqliteKnowledge::new());
+       let mut interpreter = Interpreter::new(new_knowledge("memory").unwrap());
        interpreter.clear();
        interpreter.run("add julien ami julie");
        assert_eq!(
@@ -223,7 +962,7 @@ mod tests {
     //#[test]
     //#[serial]
     //fn test_rule_1() {
-       //let mut interpreter = Interpreter::new(SqliteKnowledge::new());
+       //let mut interpreter = Interpreter::new(new_knowledge("memory").unwrap());
        //interpreter.clear();
        //interpreter.run("infer add $A ami $B -> add $B ami $A");
        //interpreter.get_rules();
@@ -232,9 +971,33 @@ mod tests {
     //}
 
     #[test]
-    #[serial]
+    fn test_run_script() {
+       let mut interpreter = Interpreter::new(new_knowledge("memory").unwrap());
+       interpreter.clear();
+       interpreter.quiet = true;
+       let script = "# friends\nadd pierre ami julie\n\nassert get $A where $A ami julie\nassert get $A where $A ami anne\nadd x y z\n";
+       assert_eq!(run_script(&mut interpreter, script.as_bytes()),
+                  Err((5, Failure::Assertion("assertion failed: get $A where $A ami anne".to_string()))));
+       assert_eq!(interpreter.knowledge.count_facts(), 1);
+       assert!(matches!(run_script(&mut interpreter, "get $A\n".as_bytes()), Err((1, Failure::Syntax(_)))));
+       assert_eq!(run_script(&mut interpreter, "add x y z\nexit\nget $A\n".as_bytes()), Ok(()));
+    }
+
+    #[test]
+    fn test_graph() {
+       let mut interpreter = Interpreter::new(new_knowledge("memory").unwrap());
+       interpreter.clear();
+       interpreter.run("add pierre ami julie and julie age 24");
+       let (format, options, query) = graph_arguments("mermaid --link ami get $A $B $C where $A $B $C").unwrap();
+       assert_eq!((format, &options.links[..], query), (GraphFormat::Mermaid, &["ami".to_string()][..], Some("get $A $B $C where $A $B $C")));
+       assert_eq!(interpreter.graph(None, GraphFormat::Dot, &options),
+                  Ok("digraph predicat {\n    \"pierre\" -> \"julie\" [label=\"ami\"];\n}\n".to_string()));
+       assert!(graph_arguments("--max-nodes").is_err());
+    }
+
+    #[test]
     fn test_rule_2() {
-       let mut interpreter = Interpreter::new(SqliteKnowledge::new());
+       let mut interpreter = Interpreter::new(new_knowledge("memory").unwrap());
        interpreter.clear();
        interpreter.run("infer add $A ami $B -> add $B ami $A");
        interpreter.run("add julien ami julie");
@@ -245,10 +1008,91 @@ mod tests {
                   );
     }
 
+    #[test]
+    fn test_rule_management() {
+       let mut interpreter = Interpreter::new(new_knowledge("memory").unwrap());
+       interpreter.clear();
+       interpreter.run("infer friendship: add $A ami $B -> add $B ami $A");
+       let rules = interpreter.knowledge.get_rules();
+       assert_eq!(rules.len(), 1);
+       assert_eq!(rules[0].name, Some("friendship".to_string()));
+       interpreter.run("disable rule friendship");
+       interpreter.run("add julien ami julie");
+       assert_eq!(
+           SimpleContext::from(vec![["julien", "ami", "julie"]]),
+           interpreter.run("get $subject $link $goal where $subject $link $goal"));
+       interpreter.run("drop rule friendship");
+       assert_eq!(interpreter.knowledge.get_rules(), vec![]);
+    }
+
+    #[test]
+    fn test_rule_storage() {
+       let mut interpreter = Interpreter::new(new_knowledge("memory").unwrap());
+       interpreter.clear();
+       interpreter.run("infer add $A 'ami de' '%|%' -> add '%|%' 'ami de' $A");
+       let rules = interpreter.knowledge.get_rules();
+       assert_eq!(rules.len(), 1);
+       assert_eq!(String::from(rules[0].conclusion.clone()), "add '%|%' 'ami de' $A");
+       interpreter.run("add julien 'ami de' '%|%'");
+       assert_eq!(
+           SimpleContext::from(vec![["%|%", "ami de", "julien"]]),
+           interpreter.run("get $subject $link $goal where $subject $link $goal and $goal == 'julien'"));
+    }
+
+    #[test]
+    fn test_rule_validation() {
+       let mut interpreter = Interpreter::new(new_knowledge("memory").unwrap());
+       interpreter.clear();
+       assert!(interpreter.run("infer add $A ami $B -> add $B ami $C").has_error());
+       assert!(!interpreter.run("infer add $A ami $B -> add $B ami $A").has_error());
+       assert!(interpreter.run("infer add $X ami $Y -> add $Y ami $X").has_error());
+       assert_eq!(interpreter.knowledge.get_rules().len(), 1);
+    }
+
+    #[test]
+    fn test_meta_command() {
+       let mut interpreter = Interpreter::new(new_knowledge("memory").unwrap());
+       interpreter.run("add julien ami julie");
+       assert_eq!(interpreter.meta_command(".clear"), Ok(()));
+       assert_eq!(interpreter.knowledge.count_facts(), 0);
+       assert!(interpreter.meta_command(".load").is_err());
+       assert!(interpreter.meta_command(".unknown").is_err());
+    }
+
+    #[test]
+    fn test_bulk_load() {
+       let interpreter = Interpreter::new(new_knowledge("memory").unwrap());
+       let file = env::temp_dir().join(format!("predicat_bulk_load_{}.tsv", std::process::id()));
+       fs::write(&file, "# friends\njulien\tami\tjulie\npierre ami anne\nbroken line\n\nanne\tami\tpierre\n").unwrap();
+       interpreter.knowledge.execute_command(&parse_command("infer add $A ami $B -> add $B ami $A")[0]).unwrap();
+       assert_eq!(interpreter.bulk_load(file.to_str().unwrap(), true), Ok((3, 1)));
+       assert_eq!(interpreter.knowledge.count_facts(), 4);
+       fs::write(&file, b"julien\tami\tanne\n\xff\tami\tjulie\n").unwrap();
+       assert!(interpreter.bulk_load(file.to_str().unwrap(), false).is_err());
+       fs::remove_file(&file).unwrap();
+       assert!(interpreter.bulk_load(file.to_str().unwrap(), true).is_err());
+       assert_eq!(parse_fact("'jean claude'\test\tgrec"), Some(("'jean claude'".to_string(), "est".to_string(), "grec".to_string())));
+    }
+
+    #[test]
+    fn test_rdf_round_trip() {
+       let mut interpreter = Interpreter::new(new_knowledge("memory").unwrap());
+       interpreter.run("add pierre ami julie and pierre age 24 and 'jean claude' est 'un homme'");
+       let file = env::temp_dir().join(format!("predicat_rdf_round_trip_{}.ttl", std::process::id()));
+       fs::write(&file, interpreter.export(RdfFormat::Turtle)).unwrap();
+       interpreter.clear();
+       assert_eq!(interpreter.import(file.to_str().unwrap(), RdfFormat::Turtle, true), Ok((3, 0)));
+       fs::remove_file(&file).unwrap();
+       assert_eq!(
+           SimpleContext::from(vec![["jean claude", "est", "un homme"]]),
+           interpreter.run("get $subject $link $goal where $subject $link $goal and $subject == 'jean claude'"));
+       assert!(interpreter.export(RdfFormat::NTriples).contains("<urn:predicat:pierre> <urn:predicat:age> \"24\" .\n"));
+    }
+
     //#[test]
     //#[serial]
     //fn test_get_command_from() {
-       //let mut interpreter = Interpreter::new(SqliteKnowledge::new());
+       //let mut interpreter = Interpreter::new(new_knowledge("memory").unwrap());
        //interpreter.clear();
        //interpreter.run("infer add $A ami $B -> add $B ami $A");
        //let cmds = interpreter.knowledge
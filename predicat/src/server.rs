@@ -0,0 +1,119 @@
+// `predicat serve`: the knowledge base over HTTP, every answer being JSON
+//   POST /query   predicat commands as the body, a line for each, the result of the last one
+//                 as an array of rows
+//   GET  /facts   every fact
+//   GET  /rules   every rule
+//   GET  /health  {"status": "ok", "facts": <number of facts>}
+// An error is answered with {"error": <message>}. The requests are handled one at a time. A command
+// which panics is answered with a 500 and the knowledge is opened again, a memory one being empty.
+
+use std::io::Read;
+use std::panic::{self, AssertUnwindSafe};
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+use knowledge::{new_knowledge, rules_to_dataframe};
+use parser::try_parse;
+use crate::Interpreter;
+
+type Answer = (u16, String);
+
+fn error(status: u16, message: &str) -> Answer {
+    (status, json!({ "error": message }).to_string())
+}
+
+fn query(interpreter: &mut Interpreter, body: &str) -> Answer {
+    let mut result = None;
+    for line in body.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if let Err(e) = try_parse(line) {
+            return error(400, &e);
+        }
+        let context = interpreter.run(line);
+        if context.has_error() {
+            return error(400, &context.log.join(", "));
+        }
+        result = Some(context.get_tab());
+    }
+    match result {
+        Some(df) => (200, df.to_json()),
+        None => error(400, "The body has no command")
+    }
+}
+
+pub fn route(interpreter: &mut Interpreter, method: &Method, url: &str, body: &str) -> Answer {
+    let path = url.split('?').next().unwrap_or(url);
+    match (method, path) {
+        (Method::Post, "/query") => query(interpreter, body),
+        (Method::Get, "/facts") => (200, interpreter.knowledge.get_all().to_json()),
+        (Method::Get, "/rules") => (200, rules_to_dataframe(&interpreter.knowledge.get_rules()).to_json()),
+        (Method::Get, "/health") =>
+            (200, json!({ "status": "ok", "facts": interpreter.knowledge.count_facts() }).to_string()),
+        (_, "/query" | "/facts" | "/rules" | "/health") =>
+            error(405, &format!("{} isn't allowed on {}", method, path)),
+        _ => error(404, &format!("There is no {}", path))
+    }
+}
+
+fn open(backend: &str) -> Result<Interpreter, String> {
+    new_knowledge(backend).map(Interpreter::new)
+}
+
+// the interpreter is left unusable by a panic, so it's replaced by a new one
+fn handle(interpreter: &mut Interpreter, backend: &str, request: &mut Request) -> Result<Answer, String> {
+    let mut body = String::new();
+    if let Err(e) = request.as_reader().read_to_string(&mut body) {
+        return Ok(error(400, &format!("The body can't be read ({})", e)));
+    }
+    let (method, url) = (request.method().clone(), request.url().to_string());
+    match panic::catch_unwind(AssertUnwindSafe(|| route(interpreter, &method, &url, &body))) {
+        Ok(answer) => Ok(answer),
+        Err(_) => {
+            *interpreter = open(backend)?;
+            Ok(error(500, "The command made the interpreter fail"))
+        }
+    }
+}
+
+fn respond(request: Request, (status, answer): Answer) {
+    let json = Header::from_bytes("Content-Type", "application/json").expect("The header is valid");
+    let response = Response::from_string(answer + "\n").with_status_code(status).with_header(json);
+    if let Err(e) = request.respond(response) {
+        eprintln!("warning: the answer couldn't be sent ({})", e);
+    }
+}
+
+pub fn serve(backend: &str, address: &str) -> Result<(), String> {
+    let mut interpreter = open(backend)?;
+    let server = Server::http(address)
+        .map_err(|e| format!("The server can't listen on {} ({})", address, e))?;
+    eprintln!("Listening on http://{}", address);
+    for mut request in server.incoming_requests() {
+        match handle(&mut interpreter, backend, &mut request) {
+            Ok(answer) => respond(request, answer),
+            Err(e) => {
+                respond(request, error(500, &e));
+                return Err(format!("The knowledge can't be opened again ({})", e));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route() {
+        let mut interpreter = open("memory").unwrap();
+        interpreter.clear();
+        assert_eq!(route(&mut interpreter, &Method::Post, "/query", "add pierre ami julie\nget $A ami $B").0, 200);
+        let (status, facts) = route(&mut interpreter, &Method::Get, "/facts", "");
+        assert_eq!((status, facts.contains("\"julie\"")), (200, true));
+        assert_eq!(route(&mut interpreter, &Method::Get, "/health", ""),
+                   (200, "{\"facts\":1,\"status\":\"ok\"}".to_string()));
+        assert_eq!(route(&mut interpreter, &Method::Post, "/query", "  ").0, 400);
+        assert_eq!(route(&mut interpreter, &Method::Post, "/query", "pierre ami").0, 400);
+        assert_eq!(route(&mut interpreter, &Method::Get, "/query", "").0, 405);
+        assert_eq!(route(&mut interpreter, &Method::Get, "/nothing", "").0, 404);
+    }
+}
@@ -0,0 +1,227 @@
+// `predicat test`: the `.pred` files are scripts run against a fresh memory knowledge,
+// their expectations checking the result of a query whatever the order of its rows:
+//   add pierre ami julie
+//   infer add $A ami $B -> add $B ami $A
+//   expect get $A $B where $A ami $B == {
+//       pierre julie
+//       julie pierre
+//   }
+//   expect get $A where $A ami julie == { pierre }
+//   expect_empty get $A where $A ami anne
+// The rows of a block are separated by new lines or `;`, their cells by spaces, a cell with
+// spaces being written between quotes ('jean claude').
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use knowledge::new_knowledge;
+use parser::try_parse;
+use crate::Interpreter;
+
+type Row = Vec<String>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Command(usize, String),
+    Expect(usize, String, Vec<Row>)
+}
+
+fn cells(row: &str) -> Result<Row, String> {
+    let mut cells = vec![];
+    let mut rest = row.trim();
+    while !rest.is_empty() {
+        let (cell, tail) = match rest.strip_prefix('\'') {
+            Some(quoted) => quoted.split_once('\'')
+                .ok_or(format!("the quote of {} isn't closed", rest))?,
+            None => rest.split_once(char::is_whitespace).unwrap_or((rest, ""))
+        };
+        cells.push(cell.to_string());
+        rest = tail.trim_start();
+    }
+    Ok(cells)
+}
+
+fn rows(block: &str) -> Result<Vec<Row>, String> {
+    block.split(['\n', ';'])
+        .filter(|row| !row.trim().is_empty())
+        .map(cells)
+        .collect()
+}
+
+pub fn parse_test(content: &str) -> Result<Vec<Step>, String> {
+    let mut steps = vec![];
+    let mut lines = content.lines().enumerate().map(|(number, line)| (number + 1, line.trim()));
+    while let Some((number, line)) = lines.next() {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(query) = line.strip_prefix("expect_empty ") {
+            steps.push(Step::Expect(number, query.trim().to_string(), vec![]));
+            continue;
+        }
+        let Some(expectation) = line.strip_prefix("expect ") else {
+            steps.push(Step::Command(number, line.to_string()));
+            continue;
+        };
+        let (query, block) = expectation.split_once(" == ")
+            .and_then(|(query, block)| Some((query, block.trim_start().strip_prefix('{')?)))
+            .ok_or(format!("line {}: an expectation is written 'expect <query> == {{ rows }}'", number))?;
+        let mut block = block.to_string();
+        while !block.contains('}') {
+            let (_, line) = lines.next().ok_or(format!("line {}: the rows aren't closed by '}}'", number))?;
+            block = block + "\n" + line;
+        }
+        let (block, after) = block.split_once('}').unwrap_or_default();
+        if !after.trim().is_empty() {
+            return Err(format!("line {}: '{}' follows the rows", number, after.trim()));
+        }
+        let rows = rows(block).map_err(|e| format!("line {}: {}", number, e))?;
+        steps.push(Step::Expect(number, query.trim().to_string(), rows));
+    }
+    Ok(steps)
+}
+
+// the missing rows then the unexpected ones, whatever their order
+fn diff(expected: &[Row], actual: &[Row]) -> Vec<String> {
+    let mut missing = expected.to_vec();
+    let mut unexpected = vec![];
+    for row in actual {
+        match missing.iter().position(|x| x == row) {
+            Some(index) => { missing.remove(index); },
+            None => unexpected.push(row)
+        }
+    }
+    missing.iter().map(|row| format!("- {}", row.join(" ")))
+        .chain(unexpected.iter().map(|row| format!("+ {}", row.join(" "))))
+        .collect()
+}
+
+fn check(interpreter: &mut Interpreter, query: &str, expected: &[Row]) -> Result<(), Vec<String>> {
+    try_parse(query).map_err(|e| vec![e])?;
+    let context = interpreter.run(query);
+    if context.has_error() {
+        return Err(context.log);
+    }
+    let df = context.get_tab();
+    let differences = diff(expected, &df.iter().collect::<Vec<_>>());
+    match differences.is_empty() {
+        true => Ok(()),
+        false => Err(std::iter::once(format!("columns: {}", df.get_variables().iter()
+                                             .map(|var| var.without_dollar()).collect::<Vec<_>>().join(" ")))
+                     .chain(differences).collect())
+    }
+}
+
+// runs the steps of a file, a failing command stopping it, and gives back the number of
+// expectations with the report of the failures
+fn run_steps(steps: &[Step]) -> Result<(usize, Vec<String>), String> {
+    let mut interpreter = Interpreter::new(new_knowledge("memory")?);
+    interpreter.quiet = true;
+    let mut failures = vec![];
+    let mut expectations = 0;
+    for step in steps {
+        match step {
+            Step::Command(number, line) => {
+                let ran = match line.starts_with('.') {
+                    true => interpreter.meta_command(line),
+                    false => interpreter.run_and_display(line).map_err(|failure| failure.message().to_string())
+                };
+                if let Err(e) = ran {
+                    failures.push(format!("line {}: {}", number, e));
+                    break;
+                }
+            },
+            Step::Expect(number, query, expected) => {
+                expectations += 1;
+                if let Err(report) = check(&mut interpreter, query, expected) {
+                    failures.push(std::iter::once(format!("line {}: {}", number, query))
+                                  .chain(report.iter().map(|line| format!("    {}", line)))
+                                  .collect::<Vec<_>>().join("\n"));
+                }
+            }
+        }
+    }
+    Ok((expectations, failures))
+}
+
+fn test_files(path: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(path) {
+        Ok(entries) => {
+            let mut paths = entries.flatten().map(|entry| entry.path()).collect::<Vec<_>>();
+            paths.sort();
+            paths.iter()
+                .flat_map(|path| match path.is_dir() {
+                    true => test_files(path),
+                    false => vec![path.clone()].into_iter()
+                        .filter(|path| path.extension().is_some_and(|x| x == "pred"))
+                        .collect()
+                })
+                .collect()
+        },
+        Err(_) => vec![path.to_path_buf()]
+    }
+}
+
+// prints the report of every file and tells if they all passed
+pub fn run(paths: &[String]) -> bool {
+    let files = paths.iter().flat_map(|path| test_files(Path::new(path))).collect::<Vec<_>>();
+    if files.is_empty() {
+        eprintln!("error: there is no .pred file in {}", paths.join(", "));
+        return false;
+    }
+    let (mut expectations, mut failed_files) = (0, 0);
+    for file in &files {
+        let outcome = fs::read_to_string(file)
+            .map_err(|e| format!("it can't be read ({})", e))
+            .and_then(|content| parse_test(&content))
+            .and_then(|steps| run_steps(&steps));
+        let failures = match outcome {
+            Ok((count, failures)) => { expectations += count; failures },
+            Err(e) => vec![e]
+        };
+        match failures.is_empty() {
+            true => println!("ok    {}", file.display()),
+            false => {
+                failed_files += 1;
+                println!("FAIL  {}", file.display());
+                failures.iter().for_each(|failure| println!("  {}", failure.replace('\n', "\n  ")));
+            }
+        }
+    }
+    println!("files: {}, expectations: {}, failed files: {}", files.len(), expectations, failed_files);
+    failed_files == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(cells: &[&str]) -> Row {
+        cells.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_test() {
+        let content = "# friends\nadd pierre ami julie\nexpect get $A $B where $A ami $B == {\n  pierre julie\n}\n\
+                       expect get $A where $A ami julie == { pierre; 'jean claude' }\nexpect_empty get $A where $A ami anne\n";
+        assert_eq!(parse_test(content), Ok(vec![
+            Step::Command(2, "add pierre ami julie".to_string()),
+            Step::Expect(3, "get $A $B where $A ami $B".to_string(), vec![row(&["pierre", "julie"])]),
+            Step::Expect(6, "get $A where $A ami julie".to_string(), vec![row(&["pierre"]), row(&["jean claude"])]),
+            Step::Expect(7, "get $A where $A ami anne".to_string(), vec![])]));
+        assert!(parse_test("expect get $A where $A ami julie == {\n pierre\n").is_err());
+        assert!(parse_test("expect get $A where $A ami julie").is_err());
+    }
+
+    #[test]
+    fn test_run_steps() {
+        let steps = parse_test("infer add $A ami $B -> add $B ami $A\nadd pierre ami julie\n\
+                                expect get $A $B where $A ami $B == { julie pierre; pierre julie }\n\
+                                expect get $A where $A ami julie == { anne }\nexpect_empty get $A where $A ami julie\n").unwrap();
+        let (expectations, failures) = run_steps(&steps).unwrap();
+        assert_eq!(expectations, 3);
+        assert_eq!(failures, vec![
+            "line 4: get $A where $A ami julie\n    columns: A\n    - anne\n    + pierre".to_string(),
+            "line 5: get $A where $A ami julie\n    columns: A\n    + pierre".to_string()]);
+        assert_eq!(run_steps(&parse_test("get $A\nexpect_empty get $A where $A ami julie").unwrap()).unwrap().1.len(), 1);
+    }
+}